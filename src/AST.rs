@@ -1,33 +1,387 @@
-use std::{rc::Rc, cell::RefCell, sync::Arc};
+use std::{rc::Rc, cell::RefCell, sync::Arc, fmt, hash::{Hash, Hasher}, mem};
 use crate::parsing::*;
 use crate::parsers::*;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ASTLocation {
-    line: u32,
-    column: u32,
+    pub line: u32,
+    pub column: u32,
 }
 
 #[derive(Debug, Clone)]
 pub enum ASTExpression {
     ASTVariableRef(String),
     ASTInteger(i64),
+    ASTFloat(f64),
+    ASTFloat32(f32),
     ASTString(String),
+    ASTInterpolation(Vec<ASTNode>),
+    ASTChar(char),
+    ASTBool(bool),
     ASTAssignment(String, Box<ASTNode>),
     ASTInitialization(String, Box<ASTNode>),
-    ASTScope(Vec<Box<ASTNode>>),
-    ASTParentheses(Vec<Box<ASTNode>>),
+    ASTLetRec(String, Box<ASTNode>),
+    ASTScope(Vec<ASTNode>),
+    ASTParentheses(Vec<ASTNode>),
+    ASTTuple(Vec<ASTNode>),
+    ASTList(Vec<ASTNode>),
+    ASTRange { start: Box<ASTNode>, end: Box<ASTNode>, inclusive: bool },
+    ASTDocComment(String),
     ASTFunction(Box<ASTNode>, Box<ASTNode>),
+    // The condition, the 'then' branch, and the 'else' branch.
+    ASTIfElse(Box<ASTNode>, Box<ASTNode>, Box<ASTNode>),
+    // The condition and the scope that runs for as long as it holds.
+    ASTWhile(Box<ASTNode>, Box<ASTNode>),
+    // The loop variable, the list being looped over, and the scope that runs for each item.
+    ASTForIn(String, Box<ASTNode>, Box<ASTNode>),
+    // An infix operator like '1 + 2', with the left side, the operator, and the right side.
+    ASTBinaryOp(Box<ASTNode>, String, Box<ASTNode>),
+    // A prefix operator like '-x' or '!done', with the operator and its operand.
+    ASTUnaryOp(String, Box<ASTNode>),
+    // Reading a field like 'point.x', with the expression being read from and the field name.
+    ASTFieldAccess(Box<ASTNode>, String),
+    // Indexing like 'items[0]', with the expression being indexed and the index.
+    ASTIndex(Box<ASTNode>, Box<ASTNode>),
+    // The value being matched on, and each arm's pattern along with the expression it runs.
+    ASTMatch(Box<ASTNode>, Vec<(ASTNode, ASTNode)>),
+    // Returns the value from the function it is in right away.
+    ASTReturn(Box<ASTNode>),
+    // Leaves the loop it is in.
+    ASTBreak,
+    // Skips to the next iteration of the loop it is in.
+    ASTContinue,
+    // The '_' pattern, which matches anything.
+    ASTWildcard,
+    ASTImport { path: Vec<String>, alias: Option<String> },
+    ASTModule(String, Vec<ASTNode>),
+    ASTTraitDef(String, Vec<ASTNode>),
+    // The struct's name, and the name and type of each of its fields.
+    ASTStructDef(String, Vec<(String, String)>),
+    // The enum's name and the names of its variants.
+    ASTEnumDef(String, Vec<String>),
+    ASTImpl { type_name: String, trait_name: Option<String>, body: Vec<ASTNode> },
+    ASTNull,
     ASTUnit,
 }
 
-#[derive(Debug, Clone)]
+// Floats are compared by their bits, so that a NaN literal is equal to itself.
+impl PartialEq for ASTExpression {
+    fn eq(&self, other: &Self) -> bool {
+        use ASTExpression::*;
+        match (self, other) {
+            (ASTVariableRef(a), ASTVariableRef(b)) => a == b,
+            (ASTInteger(a), ASTInteger(b)) => a == b,
+            (ASTFloat(a), ASTFloat(b)) => a.to_bits() == b.to_bits(),
+            (ASTFloat32(a), ASTFloat32(b)) => a.to_bits() == b.to_bits(),
+            (ASTString(a), ASTString(b)) => a == b,
+            (ASTInterpolation(a), ASTInterpolation(b)) => a == b,
+            (ASTChar(a), ASTChar(b)) => a == b,
+            (ASTBool(a), ASTBool(b)) => a == b,
+            (ASTAssignment(a_name, a), ASTAssignment(b_name, b)) => a_name == b_name && a == b,
+            (ASTInitialization(a_name, a), ASTInitialization(b_name, b)) => a_name == b_name && a == b,
+            (ASTLetRec(a_name, a), ASTLetRec(b_name, b)) => a_name == b_name && a == b,
+            (ASTScope(a), ASTScope(b)) => a == b,
+            (ASTParentheses(a), ASTParentheses(b)) => a == b,
+            (ASTTuple(a), ASTTuple(b)) => a == b,
+            (ASTList(a), ASTList(b)) => a == b,
+            (ASTRange { start: a_start, end: a_end, inclusive: a_inclusive }, ASTRange { start: b_start, end: b_end, inclusive: b_inclusive }) => {
+                a_start == b_start && a_end == b_end && a_inclusive == b_inclusive
+            },
+            (ASTDocComment(a), ASTDocComment(b)) => a == b,
+            (ASTFunction(a_args, a_body), ASTFunction(b_args, b_body)) => a_args == b_args && a_body == b_body,
+            (ASTIfElse(a_cond, a_then, a_else), ASTIfElse(b_cond, b_then, b_else)) => a_cond == b_cond && a_then == b_then && a_else == b_else,
+            (ASTWhile(a_cond, a_body), ASTWhile(b_cond, b_body)) => a_cond == b_cond && a_body == b_body,
+            (ASTForIn(a_name, a_list, a_body), ASTForIn(b_name, b_list, b_body)) => a_name == b_name && a_list == b_list && a_body == b_body,
+            (ASTBinaryOp(a_left, a_op, a_right), ASTBinaryOp(b_left, b_op, b_right)) => a_left == b_left && a_op == b_op && a_right == b_right,
+            (ASTUnaryOp(a_op, a), ASTUnaryOp(b_op, b)) => a_op == b_op && a == b,
+            (ASTFieldAccess(a, a_field), ASTFieldAccess(b, b_field)) => a == b && a_field == b_field,
+            (ASTIndex(a, a_index), ASTIndex(b, b_index)) => a == b && a_index == b_index,
+            (ASTMatch(a, a_arms), ASTMatch(b, b_arms)) => a == b && a_arms == b_arms,
+            (ASTReturn(a), ASTReturn(b)) => a == b,
+            (ASTBreak, ASTBreak) | (ASTContinue, ASTContinue) | (ASTWildcard, ASTWildcard) | (ASTNull, ASTNull) | (ASTUnit, ASTUnit) => true,
+            (ASTImport { path: a_path, alias: a_alias }, ASTImport { path: b_path, alias: b_alias }) => a_path == b_path && a_alias == b_alias,
+            (ASTModule(a_name, a), ASTModule(b_name, b)) => a_name == b_name && a == b,
+            (ASTTraitDef(a_name, a), ASTTraitDef(b_name, b)) => a_name == b_name && a == b,
+            (ASTStructDef(a_name, a_fields), ASTStructDef(b_name, b_fields)) => a_name == b_name && a_fields == b_fields,
+            (ASTEnumDef(a_name, a_variants), ASTEnumDef(b_name, b_variants)) => a_name == b_name && a_variants == b_variants,
+            (ASTImpl { type_name: a_type, trait_name: a_trait, body: a_body }, ASTImpl { type_name: b_type, trait_name: b_trait, body: b_body }) => {
+                a_type == b_type && a_trait == b_trait && a_body == b_body
+            },
+            _ => false,
+        }
+    }
+}
+
+// Comparing floats by their bits makes equality reflexive, so nodes can be used as keys in a HashMap.
+impl Eq for ASTExpression {}
+
+// Hashes the same parts that eq() compares, with floats hashed by their bits.
+impl Hash for ASTExpression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        use ASTExpression::*;
+        mem::discriminant(self).hash(state);
+        match self {
+            ASTVariableRef(name) | ASTDocComment(name) => name.hash(state),
+            ASTInteger(x) => x.hash(state),
+            ASTFloat(x) => x.to_bits().hash(state),
+            ASTFloat32(x) => x.to_bits().hash(state),
+            ASTString(x) => x.hash(state),
+            ASTChar(x) => x.hash(state),
+            ASTBool(x) => x.hash(state),
+            ASTInterpolation(nodes) | ASTScope(nodes) | ASTParentheses(nodes) | ASTTuple(nodes) | ASTList(nodes) => nodes.hash(state),
+            ASTAssignment(name, node) | ASTInitialization(name, node) | ASTLetRec(name, node) | ASTUnaryOp(name, node) => {
+                name.hash(state);
+                node.hash(state);
+            },
+            ASTRange { start, end, inclusive } => {
+                start.hash(state);
+                end.hash(state);
+                inclusive.hash(state);
+            },
+            ASTFunction(a, b) | ASTWhile(a, b) | ASTIndex(a, b) => {
+                a.hash(state);
+                b.hash(state);
+            },
+            ASTIfElse(condition, then_branch, else_branch) => {
+                condition.hash(state);
+                then_branch.hash(state);
+                else_branch.hash(state);
+            },
+            ASTForIn(name, list, body) => {
+                name.hash(state);
+                list.hash(state);
+                body.hash(state);
+            },
+            ASTBinaryOp(left, op, right) => {
+                left.hash(state);
+                op.hash(state);
+                right.hash(state);
+            },
+            ASTFieldAccess(node, field) => {
+                node.hash(state);
+                field.hash(state);
+            },
+            ASTMatch(value, arms) => {
+                value.hash(state);
+                arms.hash(state);
+            },
+            ASTReturn(node) => node.hash(state),
+            ASTBreak | ASTContinue | ASTWildcard | ASTNull | ASTUnit => {},
+            ASTImport { path, alias } => {
+                path.hash(state);
+                alias.hash(state);
+            },
+            ASTModule(name, body) | ASTTraitDef(name, body) => {
+                name.hash(state);
+                body.hash(state);
+            },
+            ASTStructDef(name, fields) => {
+                name.hash(state);
+                fields.hash(state);
+            },
+            ASTEnumDef(name, variants) => {
+                name.hash(state);
+                variants.hash(state);
+            },
+            ASTImpl { type_name, trait_name, body } => {
+                type_name.hash(state);
+                trait_name.hash(state);
+                body.hash(state);
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ASTNode {
-    expression: ASTExpression,
-    location: ASTLocation,
+    pub expression: ASTExpression,
+    pub location: ASTLocation,
+}
+
+impl ASTExpression {
+    /// Whether this is a literal value like '1', '"hi"', or 'true', which evaluates to itself
+    pub fn is_literal(&self) -> bool {
+        matches!(self,
+            ASTExpression::ASTInteger(_)
+            | ASTExpression::ASTFloat(_)
+            | ASTExpression::ASTFloat32(_)
+            | ASTExpression::ASTString(_)
+            | ASTExpression::ASTChar(_)
+            | ASTExpression::ASTBool(_)
+            | ASTExpression::ASTNull)
+    }
+
+    /// The nodes directly under this one, in the order they appear in the source.
+    /// A match gives each arm's pattern followed by its body.
+    pub fn children(&self) -> Vec<&ASTNode> {
+        match self {
+            ASTExpression::ASTInterpolation(nodes)
+            | ASTExpression::ASTScope(nodes)
+            | ASTExpression::ASTParentheses(nodes)
+            | ASTExpression::ASTTuple(nodes)
+            | ASTExpression::ASTList(nodes)
+            | ASTExpression::ASTModule(_, nodes)
+            | ASTExpression::ASTTraitDef(_, nodes)
+            | ASTExpression::ASTImpl { body: nodes, .. } => nodes.iter().collect(),
+            ASTExpression::ASTAssignment(_, value)
+            | ASTExpression::ASTInitialization(_, value)
+            | ASTExpression::ASTLetRec(_, value)
+            | ASTExpression::ASTUnaryOp(_, value)
+            | ASTExpression::ASTFieldAccess(value, _)
+            | ASTExpression::ASTReturn(value) => vec!(value),
+            ASTExpression::ASTRange { start: first, end: second, .. }
+            | ASTExpression::ASTFunction(first, second)
+            | ASTExpression::ASTWhile(first, second)
+            | ASTExpression::ASTForIn(_, first, second)
+            | ASTExpression::ASTBinaryOp(first, _, second)
+            | ASTExpression::ASTIndex(first, second) => vec!(first, second),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => vec!(condition, then_branch, else_branch),
+            ASTExpression::ASTMatch(value, arms) => {
+                let mut children = vec!(value.as_ref());
+                for (pattern, body) in arms {
+                    children.push(pattern);
+                    children.push(body);
+                }
+                children
+            },
+            ASTExpression::ASTVariableRef(_)
+            | ASTExpression::ASTInteger(_)
+            | ASTExpression::ASTFloat(_)
+            | ASTExpression::ASTFloat32(_)
+            | ASTExpression::ASTString(_)
+            | ASTExpression::ASTChar(_)
+            | ASTExpression::ASTBool(_)
+            | ASTExpression::ASTDocComment(_)
+            | ASTExpression::ASTBreak
+            | ASTExpression::ASTContinue
+            | ASTExpression::ASTWildcard
+            | ASTExpression::ASTImport { .. }
+            | ASTExpression::ASTStructDef(_, _)
+            | ASTExpression::ASTEnumDef(_, _)
+            | ASTExpression::ASTNull
+            | ASTExpression::ASTUnit => vec!(),
+        }
+    }
+
+    /// The same nodes as children(), but mutable
+    #[cfg(test)]
+    pub fn children_mut(&mut self) -> Vec<&mut ASTNode> {
+        match self {
+            ASTExpression::ASTInterpolation(nodes)
+            | ASTExpression::ASTScope(nodes)
+            | ASTExpression::ASTParentheses(nodes)
+            | ASTExpression::ASTTuple(nodes)
+            | ASTExpression::ASTList(nodes)
+            | ASTExpression::ASTModule(_, nodes)
+            | ASTExpression::ASTTraitDef(_, nodes)
+            | ASTExpression::ASTImpl { body: nodes, .. } => nodes.iter_mut().collect(),
+            ASTExpression::ASTAssignment(_, value)
+            | ASTExpression::ASTInitialization(_, value)
+            | ASTExpression::ASTLetRec(_, value)
+            | ASTExpression::ASTUnaryOp(_, value)
+            | ASTExpression::ASTFieldAccess(value, _)
+            | ASTExpression::ASTReturn(value) => vec!(value),
+            ASTExpression::ASTRange { start: first, end: second, .. }
+            | ASTExpression::ASTFunction(first, second)
+            | ASTExpression::ASTWhile(first, second)
+            | ASTExpression::ASTForIn(_, first, second)
+            | ASTExpression::ASTBinaryOp(first, _, second)
+            | ASTExpression::ASTIndex(first, second) => vec!(first, second),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => vec!(condition, then_branch, else_branch),
+            ASTExpression::ASTMatch(value, arms) => {
+                let mut children = vec!(value.as_mut());
+                for (pattern, body) in arms {
+                    children.push(pattern);
+                    children.push(body);
+                }
+                children
+            },
+            ASTExpression::ASTVariableRef(_)
+            | ASTExpression::ASTInteger(_)
+            | ASTExpression::ASTFloat(_)
+            | ASTExpression::ASTFloat32(_)
+            | ASTExpression::ASTString(_)
+            | ASTExpression::ASTChar(_)
+            | ASTExpression::ASTBool(_)
+            | ASTExpression::ASTDocComment(_)
+            | ASTExpression::ASTBreak
+            | ASTExpression::ASTContinue
+            | ASTExpression::ASTWildcard
+            | ASTExpression::ASTImport { .. }
+            | ASTExpression::ASTStructDef(_, _)
+            | ASTExpression::ASTEnumDef(_, _)
+            | ASTExpression::ASTNull
+            | ASTExpression::ASTUnit => vec!(),
+        }
+    }
+}
+
+fn fold_node<B, F: Fn(B, &ASTNode) -> B>(node: &ASTNode, accumulator: B, f: &F) -> B {
+    let accumulator = f(accumulator, node);
+    node.expression.children().into_iter().fold(accumulator, |accumulator, child| fold_node(child, accumulator, f))
+}
+
+/// Combines every node in the tree into one value, going depth first and calling f on each node before its children.
+/// Returns init when there are no nodes.
+pub fn fold<B, F: Fn(B, &ASTNode) -> B>(nodes: &[ASTNode], init: B, f: F) -> B {
+    nodes.iter().fold(init, |accumulator, node| fold_node(node, accumulator, &f))
+}
+
+fn map_box<F: Fn(ASTExpression) -> ASTExpression>(node: ASTNode, f: &F) -> Box<ASTNode> {
+    Box::new(node.map(f))
+}
+
+fn map_all<F: Fn(ASTExpression) -> ASTExpression>(nodes: Vec<ASTNode>, f: &F) -> Vec<ASTNode> {
+    nodes.into_iter().map(|node| node.map(f)).collect()
 }
 
 impl ASTNode {
+    /// Rebuilds the tree from the bottom up: every child is mapped first, then f is called on the node holding them.
+    /// Each node keeps its original location.
+    pub fn map<F: Fn(ASTExpression) -> ASTExpression>(self, f: &F) -> ASTNode {
+        let expression = match self.expression {
+            ASTExpression::ASTInterpolation(nodes) => ASTExpression::ASTInterpolation(map_all(nodes, f)),
+            ASTExpression::ASTScope(nodes) => ASTExpression::ASTScope(map_all(nodes, f)),
+            ASTExpression::ASTParentheses(nodes) => ASTExpression::ASTParentheses(map_all(nodes, f)),
+            ASTExpression::ASTTuple(nodes) => ASTExpression::ASTTuple(map_all(nodes, f)),
+            ASTExpression::ASTList(nodes) => ASTExpression::ASTList(map_all(nodes, f)),
+            ASTExpression::ASTModule(name, body) => ASTExpression::ASTModule(name, map_all(body, f)),
+            ASTExpression::ASTTraitDef(name, body) => ASTExpression::ASTTraitDef(name, map_all(body, f)),
+            ASTExpression::ASTImpl { type_name, trait_name, body } => ASTExpression::ASTImpl { type_name, trait_name, body: map_all(body, f) },
+            ASTExpression::ASTAssignment(name, value) => ASTExpression::ASTAssignment(name, map_box(*value, f)),
+            ASTExpression::ASTInitialization(name, value) => ASTExpression::ASTInitialization(name, map_box(*value, f)),
+            ASTExpression::ASTLetRec(name, value) => ASTExpression::ASTLetRec(name, map_box(*value, f)),
+            ASTExpression::ASTUnaryOp(operator, operand) => ASTExpression::ASTUnaryOp(operator, map_box(*operand, f)),
+            ASTExpression::ASTFieldAccess(target, field) => ASTExpression::ASTFieldAccess(map_box(*target, f), field),
+            ASTExpression::ASTReturn(value) => ASTExpression::ASTReturn(map_box(*value, f)),
+            ASTExpression::ASTRange { start, end, inclusive } => ASTExpression::ASTRange { start: map_box(*start, f), end: map_box(*end, f), inclusive },
+            ASTExpression::ASTFunction(parameters, body) => ASTExpression::ASTFunction(map_box(*parameters, f), map_box(*body, f)),
+            ASTExpression::ASTWhile(condition, body) => ASTExpression::ASTWhile(map_box(*condition, f), map_box(*body, f)),
+            ASTExpression::ASTForIn(name, iterable, body) => ASTExpression::ASTForIn(name, map_box(*iterable, f), map_box(*body, f)),
+            ASTExpression::ASTBinaryOp(left, operator, right) => ASTExpression::ASTBinaryOp(map_box(*left, f), operator, map_box(*right, f)),
+            ASTExpression::ASTIndex(target, index) => ASTExpression::ASTIndex(map_box(*target, f), map_box(*index, f)),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => ASTExpression::ASTIfElse(map_box(*condition, f), map_box(*then_branch, f), map_box(*else_branch, f)),
+            ASTExpression::ASTMatch(value, arms) => ASTExpression::ASTMatch(map_box(*value, f), arms.into_iter().map(|(pattern, body)| (pattern.map(f), body.map(f))).collect()),
+            expression @ (ASTExpression::ASTVariableRef(_)
+            | ASTExpression::ASTInteger(_)
+            | ASTExpression::ASTFloat(_)
+            | ASTExpression::ASTFloat32(_)
+            | ASTExpression::ASTString(_)
+            | ASTExpression::ASTChar(_)
+            | ASTExpression::ASTBool(_)
+            | ASTExpression::ASTDocComment(_)
+            | ASTExpression::ASTBreak
+            | ASTExpression::ASTContinue
+            | ASTExpression::ASTWildcard
+            | ASTExpression::ASTImport { .. }
+            | ASTExpression::ASTStructDef(_, _)
+            | ASTExpression::ASTEnumDef(_, _)
+            | ASTExpression::ASTNull
+            | ASTExpression::ASTUnit) => expression,
+        };
+        ASTNode { expression: f(expression), location: self.location }
+    }
+
     pub fn new(expression: ASTExpression, line: u32, column: u32) -> Self {
         ASTNode {
             expression,
@@ -39,46 +393,261 @@ impl ASTNode {
     }
 }
 
-pub fn parse_ast_text(text: String) -> Vec<ASTNode> {
-    let parsers: Rc<RefCell<Vec<Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String>>>>> = Rc::new(RefCell::new(vec!(
-        Box::new(parse_string_literal),
-        Box::new(parse_integer),
-        Box::new(parse_variable_ref)
-    )));
+// Escapes a character so it reads back the same inside of a string or character literal.
+// Braces are written as unicode escapes, since a plain '{' would turn the string into an interpolation.
+fn escape_char(c: char) -> String {
+    match c {
+        '\n' => String::from("\\n"),
+        '\t' => String::from("\\t"),
+        '\r' => String::from("\\r"),
+        '\\' => String::from("\\\\"),
+        '"' => String::from("\\\""),
+        '\'' => String::from("\\'"),
+        '{' | '}' => format!("\\u{:04x}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+fn escape_string(x: &str) -> String {
+    x.chars().map(escape_char).collect()
+}
+
+// Floats always need a '.' to be parsed as floats, so whole numbers get a '.0' added on.
+fn format_float(x: String) -> String {
+    if x.contains('.') || !x.chars().any(|c| c.is_ascii_digit()) { x } else { x + ".0" }
+}
+
+fn join_nodes(nodes: &[ASTNode], separator: &str) -> String {
+    nodes.iter().map(|node| node.to_string()).collect::<Vec<String>>().join(separator)
+}
+
+// Wraps a side of an infix operator in parentheses when it would otherwise be grouped differently when parsed back in, e.g. the '1 + 2' in '(1 + 2) * 3'.
+fn format_operand(node: &ASTNode, parent_operator: &str, right_side: bool) -> String {
+    let needs_parentheses = match &node.expression {
+        ASTExpression::ASTBinaryOp(_, operator, _) => match (infix_binding_power(operator), infix_binding_power(parent_operator)) {
+            (Some((left_power, right_power)), Some((parent_left_power, parent_right_power))) => {
+                if right_side { left_power < parent_right_power } else { right_power < parent_left_power }
+            },
+            _ => true,
+        },
+        _ => false,
+    };
+    if needs_parentheses { format!("({})", node) } else { node.to_string() }
+}
+
+fn format_items(nodes: &[ASTNode]) -> String {
+    if nodes.is_empty() { String::from("{}") } else { format!("{{ {} }}", join_nodes(nodes, " ")) }
+}
+
+/// Prints the expression as source code that parses back into the same expression
+impl fmt::Display for ASTExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ASTExpression::ASTVariableRef(name) => write!(f, "{}", name),
+            ASTExpression::ASTInteger(x) => write!(f, "{}", x),
+            ASTExpression::ASTFloat(x) => write!(f, "{}", format_float(x.to_string())),
+            ASTExpression::ASTFloat32(x) => write!(f, "{}f", format_float(x.to_string())),
+            ASTExpression::ASTString(x) => write!(f, "\"{}\"", escape_string(x)),
+            ASTExpression::ASTInterpolation(nodes) => {
+                write!(f, "\"")?;
+                for node in nodes {
+                    match &node.expression {
+                        ASTExpression::ASTString(x) => write!(f, "{}", escape_string(x))?,
+                        _ => write!(f, "{{{}}}", node)?,
+                    }
+                }
+                write!(f, "\"")
+            },
+            ASTExpression::ASTChar(x) => write!(f, "'{}'", escape_char(*x)),
+            ASTExpression::ASTBool(x) => write!(f, "{}", x),
+            ASTExpression::ASTAssignment(name, value) => write!(f, "{} = {}", name, value),
+            ASTExpression::ASTInitialization(name, value) => write!(f, "let {} = {}", name, value),
+            ASTExpression::ASTLetRec(name, value) => write!(f, "letrec {} = {}", name, value),
+            ASTExpression::ASTScope(nodes) => write!(f, "{}", format_items(nodes)),
+            ASTExpression::ASTParentheses(nodes) => write!(f, "({})", join_nodes(nodes, " ")),
+            // A single item tuple needs a trailing comma so that it isn't read back as parentheses.
+            ASTExpression::ASTTuple(nodes) if nodes.len() == 1 => write!(f, "({},)", nodes[0]),
+            ASTExpression::ASTTuple(nodes) => write!(f, "({})", join_nodes(nodes, ", ")),
+            ASTExpression::ASTList(nodes) => write!(f, "[{}]", join_nodes(nodes, ", ")),
+            ASTExpression::ASTRange { start, end, inclusive } => write!(f, "{}{}{}", start, if *inclusive { "..=" } else { ".." }, end),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => write!(f, "if {} then {} else {}", condition, then_branch, else_branch),
+            ASTExpression::ASTWhile(condition, body) => write!(f, "while {} {}", condition, body),
+            ASTExpression::ASTForIn(name, iterable, body) => write!(f, "for {} in {} {}", name, iterable, body),
+            ASTExpression::ASTBinaryOp(left, operator, right) => write!(f, "{} {} {}", format_operand(left, operator, false), operator, format_operand(right, operator, true)),
+            ASTExpression::ASTFieldAccess(target, field) => write!(f, "{}.{}", target, field),
+            ASTExpression::ASTIndex(target, index) => write!(f, "{}[{}]", target, index),
+            ASTExpression::ASTReturn(value) => write!(f, "return {}", value),
+            ASTExpression::ASTBreak => write!(f, "break"),
+            ASTExpression::ASTContinue => write!(f, "continue"),
+            ASTExpression::ASTMatch(value, arms) => {
+                let arms: Vec<String> = arms.iter().map(|(pattern, body)| format!("{} => {}", pattern, body)).collect();
+                write!(f, "match {} {{ {} }}", value, arms.join(", "))
+            },
+            ASTExpression::ASTUnaryOp(operator, operand) => match &operand.expression {
+                ASTExpression::ASTBinaryOp(_, _, _) => write!(f, "{}({})", operator, operand),
+                _ => write!(f, "{}{}", operator, operand),
+            },
+            // Doc comments run to the end of the line, so whatever comes after has to start on a new one.
+            ASTExpression::ASTDocComment(text) => {
+                for line in text.split('\n') {
+                    writeln!(f, "/// {}", line)?;
+                }
+                Ok(())
+            },
+            ASTExpression::ASTFunction(parameters, body) => write!(f, "{} {}", parameters, body),
+            ASTExpression::ASTImport { path, alias } => {
+                write!(f, "import {}", path.join("::"))?;
+                match alias {
+                    Some(alias) => write!(f, " as {}", alias),
+                    None => Ok(()),
+                }
+            },
+            ASTExpression::ASTModule(name, body) => write!(f, "module {} {}", name, format_items(body)),
+            ASTExpression::ASTTraitDef(name, body) => write!(f, "trait {} {}", name, format_items(body)),
+            ASTExpression::ASTEnumDef(name, variants) => {
+                if variants.is_empty() { write!(f, "enum {} {{}}", name) } else { write!(f, "enum {} {{ {} }}", name, variants.join(", ")) }
+            },
+            ASTExpression::ASTStructDef(name, fields) => {
+                let fields: Vec<String> = fields.iter().map(|(field, field_type)| format!("{}: {}", field, field_type)).collect();
+                if fields.is_empty() { write!(f, "struct {} {{}}", name) } else { write!(f, "struct {} {{ {} }}", name, fields.join(", ")) }
+            },
+            ASTExpression::ASTImpl { type_name, trait_name, body } => match trait_name {
+                Some(trait_name) => write!(f, "impl {} for {} {}", trait_name, type_name, format_items(body)),
+                None => write!(f, "impl {} {}", type_name, format_items(body)),
+            },
+            ASTExpression::ASTNull => write!(f, "null"),
+            ASTExpression::ASTWildcard => write!(f, "_"),
+            ASTExpression::ASTUnit => write!(f, "()"),
+        }
+    }
+}
+
+impl fmt::Display for ASTLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Prints the node as source code. The location is left out so the output can be parsed again, see display_with_location for that.
+impl fmt::Display for ASTNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl ASTNode {
+    /// Prints the node as source code, prefixed with where it starts (e.g. '3:5: let x = 1')
+    pub fn display_with_location(&self) -> String {
+        format!("{}: {}", self.location, self)
+    }
+}
+
+/// Everything that could be parsed out of a file, along with an error for each statement that couldn't be
+#[derive(Debug)]
+pub struct ParseFileResult {
+    pub nodes: Vec<ASTNode>,
+    pub errors: Vec<ParseError>,
+}
+
+/// Parses a whole file. The source_name is attached to any errors so they can say which file they came from.
+pub fn parse_file(text: String, source_name: Option<String>) -> ParseFileResult {
+    let parsers: Rc<ParserList> = Rc::new(RefCell::new(Vec::new()));
 
-    // To pass the main recursive parser around, you first prepare it and leak it here to make it static, then you dereference and re-reference it to make it immutable.
-    let main_parser = &*Box::leak(try_parsers_with_list(parsers.clone()));
+    // The main recursive parser gets passed into the parsers in the list, so the list parser only holds a Weak reference back to the list.
+    // That way there is no reference cycle, and everything is freed once the parsers go out of scope at the end of this function.
+    let list_parser = try_parsers_with_list(Rc::downgrade(&parsers));
+    // Postfix operators like 'a.b' and 'a[0]' start with another expression, so they can't go in the list without the list parser endlessly calling itself.
+    // Instead, the postfix parser wraps the list parser and only looks for them after an expression has been parsed.
+    let postfix_parser = parse_postfix_with_parser(Arc::new(list_parser));
+    // Infix operators and ranges go around everything else, so each side of an operator can be any other kind of expression.
+    let main_parser: InteriorParser = Arc::new(parse_binary_op_with_parser(Arc::new(postfix_parser)));
 
     // Here, I create the recursive parsers by passing the main_parser into multiple functions.
     // Wrapping main_parser in an Arc instead of a Rc adds some overhead, but should allow for multi-threaded parsing down the line. 
-    let scope_parser = parse_scope_with_parser(Arc::new(main_parser));
-    let parentheses_parser = parse_parentheses_with_parser(Arc::new(main_parser));
-    let function_parser = parse_function_with_parser(Arc::new(main_parser));
-    let assignment_parser = parse_assignment_with_parser(Arc::new(main_parser));
+    let scope_parser = parse_scope_with_parser(main_parser.clone());
+    let parentheses_parser = parse_parentheses_with_parser(main_parser.clone());
+    let function_parser = parse_function_with_parser(main_parser.clone());
+    let assignment_parser = parse_assignment_with_parser(main_parser.clone());
+    let let_rec_parser = parse_let_rec_with_parser(main_parser.clone());
+    let module_parser = parse_module_with_parser(main_parser.clone());
+    let trait_def_parser = parse_trait_def_with_parser(main_parser.clone());
+    let impl_block_parser = parse_impl_block_with_parser(main_parser.clone());
+    let interpolation_parser = parse_interpolation_with_parser(main_parser.clone());
+    let tuple_parser = parse_tuple_with_parser(main_parser.clone());
+    let list_parser = parse_list_with_parser(main_parser.clone());
+    let if_else_parser = parse_if_else_with_parser(main_parser.clone());
+    let match_parser = parse_match_with_parser(main_parser.clone());
+    let return_parser = parse_return_with_parser(main_parser.clone());
+    let while_parser = parse_while_with_parser(main_parser.clone());
+    let for_in_parser = parse_for_in_with_parser(main_parser.clone());
 
-    // After constructing the scope_parser and passing the main parser into it, I then add the scope_parser into the main parser.
+    // After constructing the recursive parsers and passing the main parser into them, I then add them into the main parser.
     // This allows for endless recursive parsing, but it also makes the type definitions explode in length.
     // A Rc<RefCell<Vec<Box<...>>>> doesn't exactly roll off the tongue.
-    parsers.borrow_mut().push(Box::new(scope_parser));
-    parsers.borrow_mut().push(Box::new(parentheses_parser));
-    parsers.borrow_mut().insert(0, Box::new(function_parser));
-    parsers.borrow_mut().insert(1, Box::new(assignment_parser));
+    // The parsers are tried in order, so stricter patterns need to come before more permissive ones (e.g. '0x1' before '0', and keywords before variable names).
+    parsers.borrow_mut().extend(vec!(
+        Box::new(parse_doc_comment) as BoxedParser,
+        Box::new(function_parser),
+        Box::new(if_else_parser),
+        Box::new(match_parser),
+        Box::new(return_parser),
+        Box::new(while_parser),
+        Box::new(for_in_parser),
+        Box::new(let_rec_parser),
+        Box::new(assignment_parser),
+        Box::new(module_parser),
+        Box::new(trait_def_parser),
+        Box::new(impl_block_parser),
+        Box::new(parse_raw_string),
+        Box::new(parse_multiline_string),
+        Box::new(interpolation_parser),
+        Box::new(parse_string_literal),
+        Box::new(parse_float),
+        Box::new(parse_hex_integer),
+        Box::new(parse_octal_integer),
+        Box::new(parse_binary_integer),
+        Box::new(parse_integer),
+        Box::new(parse_char_literal),
+        Box::new(parse_import),
+        Box::new(parse_struct_def),
+        Box::new(parse_enum_def),
+        Box::new(parse_null),
+        Box::new(parse_bool),
+        Box::new(parse_break),
+        Box::new(parse_continue),
+        Box::new(parse_variable_ref),
+        Box::new(parse_operator_ref),
+        Box::new(scope_parser),
+        Box::new(tuple_parser),
+        Box::new(list_parser),
+        Box::new(parentheses_parser),
+    ));
 
-    let mut input = ParseInput::new(text);
+    let mut input = match &source_name {
+        Some(source_name) => ParseInput::with_name(text, source_name.clone()),
+        None => ParseInput::new(text),
+    };
 
-    let mut ast_tree: Vec<ASTNode> = Vec::new();
+    let mut result = ParseFileResult { nodes: Vec::new(), errors: Vec::new() };
 
-    // The below loop should probably go into a parse_file function somewhere.
-    // If I make a Parser type for my parsing functions, maybe it could live in that type as a static method.
     loop {
+        // An unclosed block comment runs to the end of the file, so there is nothing left to parse after it.
+        if let Err(err) = input.skip_spaces_and_newlines() {
+            result.errors.push(ParseError { source_name: source_name.clone(), ..err });
+            break;
+        }
         if !input.finished() {
+            let save_point = input.create_save_point();
             match main_parser(&mut input) {
                 Ok(expr) => {
-                    ast_tree.push(expr);
+                    result.nodes.push(expr);
                 },
+                // After an error, parsing picks back up on the next line so that the rest of the file still gets parsed.
                 Err(err) => {
-                    eprintln!("{}", err);
-                    break;
+                    // Errors made from a single character don't know the file name, so it gets filled in here.
+                    result.errors.push(ParseError { source_name: source_name.clone(), ..err });
+                    input.load_save_point(save_point);
+                    input.pop_until_char('\n');
                 }
             }
         } else {
@@ -86,5 +655,233 @@ pub fn parse_ast_text(text: String) -> Vec<ASTNode> {
         }
     }
 
-    ast_tree
-}
\ No newline at end of file
+    result
+}
+
+/// Parses a file, printing any errors to stderr and returning the nodes that could be parsed
+pub fn parse_ast_text(text: String) -> Vec<ASTNode> {
+    let source = ParseInput::new(text.clone());
+    let result = parse_file(text, None);
+    for error in &result.errors {
+        eprintln!("{}", error.display(&source));
+    }
+    result.nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<ASTNode> {
+        let ParseFileResult { nodes, errors } = parse_file(text.to_string(), None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        nodes
+    }
+
+    // Printing a program and parsing it again should give back the same tree.
+    // The trees are compared as S-expressions, since the printed source doesn't keep the original spacing (and so the original locations).
+    #[test]
+    fn display_round_trips_through_the_parser() {
+        let sources = [
+            "42",
+            "0x1F",
+            "0b101",
+            "1.0",
+            "2.5f",
+            "-3.25",
+            "\"hello\"",
+            "\"tab\\t quote\\\" backslash\\\\ newline\\n\"",
+            "\"brace \\u007b ok\"",
+            "r\"raw \\n\"",
+            "\"\"\"two\nlines\"\"\"",
+            "\"a{x}b\"",
+            "\"{(+ 1 2)}\"",
+            "'c'",
+            "'\\''",
+            "'\\n'",
+            "true",
+            "false",
+            "null",
+            "x",
+            "_unused",
+            "let x = 1",
+            "letrec f = (n) { (f n) }",
+            "x = 2",
+            "{ }",
+            "{ let a = 1\n a }",
+            "(f x y)",
+            "(f)",
+            "(1, \"two\", 'c')",
+            "(1,)",
+            "[1, 2, 3]",
+            "[]",
+            "1..3",
+            "1..=3",
+            "(x y) { (+ x y) }",
+            "() { 1 }",
+            "if c then 1 else 2",
+            "if (== x 0) then { 1 } else if b then 2 else 3",
+            "while (< i 10) { i = (+ i 1) }",
+            "for c in \"abc\" { (print c) }",
+            "x == 1",
+            "-x",
+            "p.x",
+            "xs[0]",
+            "(f) { return 1 }",
+            "while true { break }",
+            "for i in 0..3 { continue }",
+            "match x { 1 => a, _ => b }",
+            "import a::b as c",
+            "import x::*",
+            "module M { let a = 1 }",
+            "trait Show { (self) }",
+            "struct Point { x: Int, y: Int }",
+            "enum Color { Red, Green }",
+            "impl Show for Int { let show = (self) { self } }",
+            "impl Int { }",
+            "/// first\n/// second\nlet documented = 1",
+        ];
+        for source in sources {
+            let nodes = parse(source);
+            let printed = nodes.iter().map(|node| node.to_string()).collect::<Vec<String>>().join("\n");
+            let reparsed = parse(&printed);
+            assert_eq!(crate::sexp::ast_list_to_sexp(&reparsed), crate::sexp::ast_list_to_sexp(&nodes), "{} printed as {}", source, printed);
+        }
+    }
+
+    #[test]
+    fn display_with_location_starts_with_line_and_column() {
+        let nodes = parse("let x = 1\n  (f x)");
+        let lines: Vec<String> = nodes.iter().map(|node| node.display_with_location()).collect();
+        assert_eq!(lines, vec!("1:1: let x = 1", "2:3: (f x)"));
+    }
+
+    #[test]
+    fn only_plain_values_are_literals() {
+        assert!(ASTExpression::ASTInteger(1).is_literal());
+        assert!(ASTExpression::ASTString(String::from("a")).is_literal());
+        assert!(ASTExpression::ASTNull.is_literal());
+        assert!(!ASTExpression::ASTScope(vec!()).is_literal());
+        assert!(!ASTExpression::ASTVariableRef(String::from("x")).is_literal());
+        assert!(!ASTExpression::ASTList(vec!()).is_literal());
+    }
+
+    #[test]
+    fn children_are_in_source_order() {
+        let nodes = parse("if c then 1 else 2\nmatch x { 1 => a, _ => b }");
+        let if_children: Vec<String> = nodes[0].expression.children().iter().map(|node| node.to_string()).collect();
+        assert_eq!(if_children, vec!("c", "1", "2"));
+        let match_children: Vec<String> = nodes[1].expression.children().iter().map(|node| node.to_string()).collect();
+        assert_eq!(match_children, vec!("x", "1", "a", "_", "b"));
+        assert!(ASTExpression::ASTInteger(1).children().is_empty());
+    }
+
+    #[test]
+    fn children_mut_can_change_each_child() {
+        let mut nodes = parse("(+ 1 2)");
+        for child in nodes[0].expression.children_mut() {
+            if let ASTExpression::ASTInteger(x) = &mut child.expression {
+                *x *= 10;
+            }
+        }
+        assert_eq!(nodes[0].to_string(), "(+ 10 20)");
+    }
+
+    fn increment_integers(expression: ASTExpression) -> ASTExpression {
+        match expression {
+            ASTExpression::ASTInteger(x) => ASTExpression::ASTInteger(x + 1),
+            expression => expression,
+        }
+    }
+
+    #[test]
+    fn map_increments_every_integer() {
+        let nodes = parse("{ let x = 1 { (+ 2 { [3, 4] }) } }");
+        let mapped: Vec<ASTNode> = nodes.iter().cloned().map(|node| node.map(&increment_integers)).collect();
+        assert_eq!(mapped[0].to_string(), parse("{ let x = 2 { (+ 3 { [4, 5] }) } }")[0].to_string());
+        let locations = |nodes: &[ASTNode]| fold(nodes, vec!(), |mut locations, node| {
+            locations.push((node.location.line, node.location.column));
+            locations
+        });
+        assert_eq!(locations(&mapped), locations(&nodes));
+    }
+
+    #[test]
+    fn map_calls_f_on_children_first() {
+        let calls = RefCell::new(vec!());
+        parse("{ (1) }")[0].clone().map(&|expression| {
+            calls.borrow_mut().push(expression.to_string());
+            expression
+        });
+        assert_eq!(calls.into_inner(), vec!("1", "(1)", "{ (1) }"));
+    }
+
+    #[test]
+    fn fold_counts_nested_integers() {
+        let count = fold(&parse("(+ 1 { let x = 2 [3, (f 4)] })\n5"), 0, |count, node| match node.expression {
+            ASTExpression::ASTInteger(_) => count + 1,
+            _ => count,
+        });
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn fold_collects_strings_in_order() {
+        let strings = fold(&parse("{ \"a\" (f \"b\") { \"c\" } }"), vec!(), |mut strings, node| {
+            if let ASTExpression::ASTString(x) = &node.expression {
+                strings.push(x.clone());
+            }
+            strings
+        });
+        assert_eq!(strings, vec!("a", "b", "c"));
+    }
+
+    #[test]
+    fn fold_over_nothing_returns_init() {
+        assert_eq!(fold(&[], 42, |_, _| 0), 42);
+    }
+
+    #[test]
+    fn parsing_recovers_after_a_bad_statement() {
+        let result = parse_file(String::from("let x = 1\nlet y = )\nlet z = 3"), None);
+        assert_eq!(result.nodes.len(), 2);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].line, 2);
+        assert_eq!(result.nodes[1].to_string(), "let z = 3");
+    }
+
+    // Counts the bytes each thread has allocated and not freed, so a test can check its own allocations while other tests run.
+    struct CountingAllocator;
+
+    thread_local! {
+        static LIVE_BYTES: std::cell::Cell<isize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + layout.size() as isize));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            let _ = LIVE_BYTES.try_with(|live| live.set(live.get() - layout.size() as isize));
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn parsing_does_not_leak() {
+        let text = "letrec f = (n) { if (< n 1) then 0 else (f (- n 1)) }\n(f [1, 2, 3])";
+        // The first parse can set up things that live for the whole run, like the stderr handle.
+        drop(parse_ast_text(text.to_string()));
+        let before = LIVE_BYTES.with(|live| live.get());
+        for _ in 0..1000 {
+            drop(parse_ast_text(text.to_string()));
+        }
+        let after = LIVE_BYTES.with(|live| live.get());
+        assert!(after - before < 1024, "{} bytes leaked", after - before);
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use crate::vm::*;
+
+const MAGIC: &[u8; 4] = b"LCBC";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct DeserError {
+    pub message: String,
+    pub offset: usize,
+}
+
+// Opcodes for each instruction. These are part of the file format, so existing numbers should never be changed.
+const OP_PUSH: u8 = 0;
+const OP_POP: u8 = 1;
+const OP_ADD: u8 = 2;
+const OP_SUB: u8 = 3;
+const OP_MUL: u8 = 4;
+const OP_DIV: u8 = 5;
+const OP_LOAD: u8 = 6;
+const OP_STORE: u8 = 7;
+const OP_CALL: u8 = 8;
+const OP_RETURN: u8 = 9;
+const OP_JUMP: u8 = 10;
+const OP_JUMP_IF_FALSE: u8 = 11;
+const OP_MAKE_FUNC: u8 = 12;
+
+const VALUE_INT: u8 = 0;
+const VALUE_FLOAT: u8 = 1;
+const VALUE_STR: u8 = 2;
+const VALUE_CHAR: u8 = 3;
+const VALUE_BOOL: u8 = 4;
+const VALUE_FUN: u8 = 5;
+const VALUE_UNIT: u8 = 6;
+
+fn write_u64(output: &mut Vec<u8>, x: u64) {
+    output.extend_from_slice(&x.to_le_bytes());
+}
+
+fn write_string(output: &mut Vec<u8>, x: &str) {
+    write_u64(output, x.len() as u64);
+    output.extend_from_slice(x.as_bytes());
+}
+
+fn write_value(output: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Int(x) => {
+            output.push(VALUE_INT);
+            output.extend_from_slice(&x.to_le_bytes());
+        },
+        Value::Float(x) => {
+            output.push(VALUE_FLOAT);
+            output.extend_from_slice(&x.to_le_bytes());
+        },
+        Value::Str(x) => {
+            output.push(VALUE_STR);
+            write_string(output, x);
+        },
+        Value::Char(x) => {
+            output.push(VALUE_CHAR);
+            output.extend_from_slice(&(*x as u32).to_le_bytes());
+        },
+        Value::Bool(x) => {
+            output.push(VALUE_BOOL);
+            output.push(*x as u8);
+        },
+        Value::Fun(parameter, address, captured) => {
+            output.push(VALUE_FUN);
+            write_string(output, parameter);
+            write_u64(output, *address as u64);
+            // Sorting the captured variables keeps the output the same between runs, since HashMap order isn't stable.
+            let mut captured: Vec<(&String, &Value)> = captured.iter().collect();
+            captured.sort_by_key(|(name, _)| *name);
+            write_u64(output, captured.len() as u64);
+            for (name, value) in captured {
+                write_string(output, name);
+                write_value(output, value);
+            }
+        },
+        Value::Unit => output.push(VALUE_UNIT),
+    }
+}
+
+/// Encodes instructions as the magic bytes and version, followed by each instruction's opcode and operands
+pub fn serialize_instructions(instructions: &[Instruction]) -> Vec<u8> {
+    let mut output = MAGIC.to_vec();
+    output.push(VERSION);
+    for instruction in instructions {
+        match instruction {
+            Instruction::Push(value) => {
+                output.push(OP_PUSH);
+                write_value(&mut output, value);
+            },
+            Instruction::Pop => output.push(OP_POP),
+            Instruction::Add => output.push(OP_ADD),
+            Instruction::Sub => output.push(OP_SUB),
+            Instruction::Mul => output.push(OP_MUL),
+            Instruction::Div => output.push(OP_DIV),
+            Instruction::Load(name) => {
+                output.push(OP_LOAD);
+                write_string(&mut output, name);
+            },
+            Instruction::Store(name) => {
+                output.push(OP_STORE);
+                write_string(&mut output, name);
+            },
+            Instruction::Call(argument_count) => {
+                output.push(OP_CALL);
+                write_u64(&mut output, *argument_count as u64);
+            },
+            Instruction::Return => output.push(OP_RETURN),
+            Instruction::Jump(offset) => {
+                output.push(OP_JUMP);
+                write_u64(&mut output, *offset as u64);
+            },
+            Instruction::JumpIfFalse(offset) => {
+                output.push(OP_JUMP_IF_FALSE);
+                write_u64(&mut output, *offset as u64);
+            },
+            Instruction::MakeFunc(parameter, body_length) => {
+                output.push(OP_MAKE_FUNC);
+                write_string(&mut output, parameter);
+                write_u64(&mut output, *body_length as u64);
+            },
+        }
+    }
+    output
+}
+
+// Reads through the bytes with a cursor, much like ParseInput does for source text.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn error(&self, message: &str) -> DeserError {
+        DeserError { message: message.to_string(), offset: self.position }
+    }
+
+    fn finished(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], DeserError> {
+        // A corrupt length prefix can be big enough to overflow, which is just as much past the end as any other bad length.
+        let end = self.position.checked_add(count).ok_or(self.error("Unexpected end of bytecode"))?;
+        let bytes = self.bytes.get(self.position..end).ok_or(self.error("Unexpected end of bytecode"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DeserError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_8_bytes(&mut self) -> Result<[u8; 8], DeserError> {
+        let mut buffer = [0; 8];
+        buffer.copy_from_slice(self.read_bytes(8)?);
+        Ok(buffer)
+    }
+
+    fn read_usize(&mut self) -> Result<usize, DeserError> {
+        let x = u64::from_le_bytes(self.read_8_bytes()?);
+        usize::try_from(x).map_err(|_| self.error("Number is too large for this platform"))
+    }
+
+    fn read_string(&mut self) -> Result<String, DeserError> {
+        let length = self.read_usize()?;
+        let bytes = self.read_bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("Invalid UTF-8 in string"))
+    }
+
+    fn read_value(&mut self) -> Result<Value, DeserError> {
+        let value = match self.read_u8()? {
+            VALUE_INT => Value::Int(i64::from_le_bytes(self.read_8_bytes()?)),
+            VALUE_FLOAT => Value::Float(f64::from_le_bytes(self.read_8_bytes()?)),
+            VALUE_STR => Value::Str(self.read_string()?),
+            VALUE_CHAR => {
+                let mut buffer = [0; 4];
+                buffer.copy_from_slice(self.read_bytes(4)?);
+                Value::Char(char::from_u32(u32::from_le_bytes(buffer)).ok_or(self.error("Invalid character"))?)
+            },
+            VALUE_BOOL => Value::Bool(self.read_u8()? != 0),
+            VALUE_FUN => {
+                let parameter = self.read_string()?;
+                let address = self.read_usize()?;
+                let captured_count = self.read_usize()?;
+                let mut captured = HashMap::new();
+                for _ in 0..captured_count {
+                    let name = self.read_string()?;
+                    let value = self.read_value()?;
+                    captured.insert(name, value);
+                }
+                Value::Fun(parameter, address, captured)
+            },
+            VALUE_UNIT => Value::Unit,
+            _ => return Err(self.error("Unknown value tag")),
+        };
+        Ok(value)
+    }
+}
+
+/// Decodes bytes made by serialize_instructions, checking the magic bytes and version first
+pub fn deserialize_instructions(bytes: &[u8]) -> Result<Vec<Instruction>, DeserError> {
+    let mut reader = ByteReader { bytes, position: 0 };
+    if reader.read_bytes(MAGIC.len())? != MAGIC {
+        return Err(DeserError { message: String::from("Missing LCBC magic bytes"), offset: 0 });
+    }
+    let version = reader.read_u8()?;
+    if version != VERSION {
+        return Err(reader.error(&format!("Unsupported bytecode version {}", version)));
+    }
+    let mut instructions = vec!();
+    while !reader.finished() {
+        let instruction = match reader.read_u8()? {
+            OP_PUSH => Instruction::Push(reader.read_value()?),
+            OP_POP => Instruction::Pop,
+            OP_ADD => Instruction::Add,
+            OP_SUB => Instruction::Sub,
+            OP_MUL => Instruction::Mul,
+            OP_DIV => Instruction::Div,
+            OP_LOAD => Instruction::Load(reader.read_string()?),
+            OP_STORE => Instruction::Store(reader.read_string()?),
+            OP_CALL => Instruction::Call(reader.read_usize()?),
+            OP_RETURN => Instruction::Return,
+            OP_JUMP => Instruction::Jump(reader.read_usize()?),
+            OP_JUMP_IF_FALSE => Instruction::JumpIfFalse(reader.read_usize()?),
+            OP_MAKE_FUNC => {
+                let parameter = reader.read_string()?;
+                Instruction::MakeFunc(parameter, reader.read_usize()?)
+            },
+            _ => return Err(reader.error("Unknown opcode")),
+        };
+        instructions.push(instruction);
+    }
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AST::*;
+
+    #[test]
+    fn compiled_program_round_trips() {
+        let ParseFileResult { nodes, errors } = parse_file(String::from("let f = (x) { if true then (+ x 1) else 0 }\n(f 2.5)\n\"done\""), None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let instructions = compile_ast(&nodes).unwrap();
+        let bytes = serialize_instructions(&instructions);
+        assert_eq!(deserialize_instructions(&bytes).unwrap(), instructions);
+    }
+
+    // One of each kind of value, with the edge cases of each. The match has no catch-all, so a new value tag has to be added here too.
+    fn sample_values() -> Vec<Value> {
+        let captured = HashMap::from([
+            (String::from("y"), Value::Char('λ')),
+            (String::from("inner"), Value::Fun(String::new(), 0, HashMap::new())),
+        ]);
+        let values = vec!(
+            Value::Int(0),
+            Value::Int(i64::MIN),
+            Value::Int(i64::MAX),
+            Value::Float(-2.5),
+            Value::Float(f64::INFINITY),
+            Value::Str(String::new()),
+            Value::Str(String::from("héllo 👋")),
+            Value::Char('a'),
+            Value::Char('\u{10FFFF}'),
+            Value::Bool(true),
+            Value::Bool(false),
+            Value::Fun(String::from("x"), 3, captured),
+            Value::Unit,
+        );
+        for value in &values {
+            match value {
+                Value::Int(_) | Value::Float(_) | Value::Str(_) | Value::Char(_) | Value::Bool(_) | Value::Fun(_, _, _) | Value::Unit => {},
+            }
+        }
+        values
+    }
+
+    // Every instruction with a few different operands, including every sample value being pushed.
+    fn sample_instructions() -> Vec<Instruction> {
+        let mut instructions: Vec<Instruction> = sample_values().into_iter().map(Instruction::Push).collect();
+        for operand in [0, 1, usize::MAX] {
+            instructions.extend(vec!(
+                Instruction::Call(operand),
+                Instruction::Jump(operand),
+                Instruction::JumpIfFalse(operand),
+                Instruction::MakeFunc(String::from("x"), operand),
+            ));
+        }
+        instructions.extend(vec!(
+            Instruction::Pop,
+            Instruction::Add,
+            Instruction::Sub,
+            Instruction::Mul,
+            Instruction::Div,
+            Instruction::Load(String::from("x")),
+            Instruction::Store(String::from("some-name")),
+            Instruction::Return,
+        ));
+        for instruction in &instructions {
+            match instruction {
+                Instruction::Push(_)
+                | Instruction::Pop
+                | Instruction::Add
+                | Instruction::Sub
+                | Instruction::Mul
+                | Instruction::Div
+                | Instruction::Load(_)
+                | Instruction::Store(_)
+                | Instruction::Call(_)
+                | Instruction::Return
+                | Instruction::Jump(_)
+                | Instruction::JumpIfFalse(_)
+                | Instruction::MakeFunc(_, _) => {},
+            }
+        }
+        instructions
+    }
+
+    #[test]
+    fn every_instruction_round_trips() {
+        let instructions = sample_instructions();
+        for instruction in &instructions {
+            let bytes = serialize_instructions(std::slice::from_ref(instruction));
+            assert_eq!(deserialize_instructions(&bytes).unwrap(), vec!(instruction.clone()));
+        }
+        assert_eq!(deserialize_instructions(&serialize_instructions(&instructions)).unwrap(), instructions);
+    }
+
+    #[test]
+    fn generated_programs_round_trip() {
+        let samples = sample_instructions();
+        // A small linear congruential generator, so the programs are random looking but the same on every run.
+        let mut seed: u64 = 0x2545F4914F6CDD1D;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+        for _ in 0..200 {
+            let length = next() % 20;
+            let program: Vec<Instruction> = (0..length).map(|_| samples[next() % samples.len()].clone()).collect();
+            assert_eq!(deserialize_instructions(&serialize_instructions(&program)).unwrap(), program);
+        }
+    }
+
+    #[test]
+    fn every_truncation_is_an_error_or_a_shorter_program() {
+        let instructions = sample_instructions();
+        let bytes = serialize_instructions(&instructions);
+        for end in 0..bytes.len() {
+            if let Ok(decoded) = deserialize_instructions(&bytes[..end]) {
+                assert!(instructions.starts_with(&decoded));
+            }
+        }
+    }
+
+    #[test]
+    fn huge_length_prefix_is_an_error() {
+        let mut bytes = b"LCBC\x01".to_vec();
+        bytes.extend([OP_PUSH, VALUE_STR]);
+        bytes.extend([0xff; 8]);
+        let err = deserialize_instructions(&bytes).unwrap_err();
+        assert_eq!((err.message.as_str(), err.offset), ("Unexpected end of bytecode", 15));
+        let mut bytes = b"LCBC\x01".to_vec();
+        bytes.push(OP_LOAD);
+        bytes.extend(1000u64.to_le_bytes());
+        bytes.extend(b"abc");
+        assert!(deserialize_instructions(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_magic_and_truncated_input() {
+        assert_eq!(deserialize_instructions(b"NOPE\x01").unwrap_err().offset, 0);
+        let bytes = serialize_instructions(&[Instruction::Push(Value::Int(1))]);
+        assert!(deserialize_instructions(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+pub const USAGE: &str = "\
+Usage: language_compiler --input <FILE> [OPTIONS]
+       language_compiler --files <FILE>... [OPTIONS]
+       language_compiler --run-bytecode <FILE>
+       language_compiler --repl
+
+Options:
+  --input <FILE>        The source file to compile
+  --files <FILE>...     Compile several source files together as one program
+  --print-ast           Print the parsed AST
+  --json                Print the parsed AST as JSON
+  --sexp                Print the parsed AST as S-expressions
+  --output <FILE>       Write the output to a file instead of stdout
+  --backend <c|js|vm>   Generate C, JavaScript, or bytecode instead of interpreting the file
+  --uncurry             Give JavaScript functions all of their parameters at once instead of currying them
+  --output-bytecode <FILE>
+                        Compile the file to bytecode and write it to a file
+  --run-bytecode <FILE> Run a bytecode file written with '--output-bytecode' on the VM
+  --optimize            Work out constant expressions and remove unreachable code before running or generating code
+  --check               Check names and types before running the program, and stop if there are any errors
+  --watch               Re-parse the input file and print its AST every time it changes
+  --repl                Start an interactive session
+  --color <auto|always|never>
+                        Whether to color error messages (auto colors them when stderr is a terminal)
+  --json-errors         Print parse errors as JSON instead of human readable text
+  --help                Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    C,
+    Js,
+    Vm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    pub input: Option<String>,
+    pub files: Vec<String>,
+    pub print_ast: bool,
+    pub json: bool,
+    pub sexp: bool,
+    pub output: Option<String>,
+    pub backend: Option<Backend>,
+    pub uncurry: bool,
+    pub bytecode_output: Option<String>,
+    pub bytecode_input: Option<String>,
+    pub optimize: bool,
+    pub check: bool,
+    pub watch: bool,
+    pub repl: bool,
+    pub color: ColorChoice,
+    pub json_errors: bool,
+    pub help: bool,
+}
+
+fn parse_backend(name: &str) -> Result<Backend, String> {
+    match name {
+        "c" => Ok(Backend::C),
+        "js" => Ok(Backend::Js),
+        "vm" => Ok(Backend::Vm),
+        _ => Err(format!("Unknown backend '{}', expected one of c, js, or vm", name)),
+    }
+}
+
+fn parse_color(name: &str) -> Result<ColorChoice, String> {
+    match name {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        _ => Err(format!("Unknown color choice '{}', expected one of auto, always, or never", name)),
+    }
+}
+
+/// Parses the arguments after the program name. '--input' or '--files' is required unless '--run-bytecode', '--repl' or '--help' is given.
+pub fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut cli_args = CliArgs::default();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        // '--files' takes every argument after it, up until the next option.
+        if arg == "--files" {
+            while let Some(path) = args.next_if(|next| !next.starts_with("--")) {
+                cli_args.files.push(path.clone());
+            }
+            if cli_args.files.is_empty() {
+                return Err(String::from("Expected at least one file after '--files'"));
+            }
+            continue;
+        }
+        let mut value = || args.next().cloned().ok_or(format!("Expected a value after '{}'", arg));
+        match arg.as_str() {
+            "--input" => cli_args.input = Some(value()?),
+            "--print-ast" => cli_args.print_ast = true,
+            "--json" => cli_args.json = true,
+            "--sexp" => cli_args.sexp = true,
+            "--output" => cli_args.output = Some(value()?),
+            "--backend" => cli_args.backend = Some(parse_backend(&value()?)?),
+            "--uncurry" => cli_args.uncurry = true,
+            "--output-bytecode" => cli_args.bytecode_output = Some(value()?),
+            "--run-bytecode" => cli_args.bytecode_input = Some(value()?),
+            "--optimize" => cli_args.optimize = true,
+            "--check" => cli_args.check = true,
+            "--watch" => cli_args.watch = true,
+            "--repl" => cli_args.repl = true,
+            "--color" => cli_args.color = parse_color(&value()?)?,
+            color if color.starts_with("--color=") => cli_args.color = parse_color(&color["--color=".len()..])?,
+            "--json-errors" => cli_args.json_errors = true,
+            "--help" | "-h" => cli_args.help = true,
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+    }
+    if cli_args.input.is_none() && cli_args.files.is_empty() && cli_args.bytecode_input.is_none() && !cli_args.repl && !cli_args.help {
+        return Err(String::from("Missing required argument '--input <FILE>' or '--files <FILE>...'"));
+    }
+    Ok(cli_args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, String> {
+        parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>())
+    }
+
+    #[test]
+    fn input_on_its_own() {
+        let cli_args = parse(&["--input", "main.txt"]).unwrap();
+        assert_eq!(cli_args.input.as_deref(), Some("main.txt"));
+        assert!(!cli_args.print_ast && !cli_args.check && !cli_args.help);
+        assert_eq!(cli_args.backend, None);
+        assert_eq!(cli_args.color, ColorChoice::Auto);
+    }
+
+    // An option that doesn't take a value, along with how to tell that it was given.
+    type Switch = (&'static str, fn(&CliArgs) -> bool);
+
+    #[test]
+    fn each_switch_sets_its_field() {
+        let switches: [Switch; 11] = [
+            ("--print-ast", |cli_args| cli_args.print_ast),
+            ("--json", |cli_args| cli_args.json),
+            ("--sexp", |cli_args| cli_args.sexp),
+            ("--uncurry", |cli_args| cli_args.uncurry),
+            ("--optimize", |cli_args| cli_args.optimize),
+            ("--check", |cli_args| cli_args.check),
+            ("--watch", |cli_args| cli_args.watch),
+            ("--repl", |cli_args| cli_args.repl),
+            ("--json-errors", |cli_args| cli_args.json_errors),
+            ("--help", |cli_args| cli_args.help),
+            ("-h", |cli_args| cli_args.help),
+        ];
+        for (switch, is_set) in switches {
+            assert!(is_set(&parse(&["--input", "main.txt", switch]).unwrap()), "{}", switch);
+            assert!(!is_set(&parse(&["--input", "main.txt"]).unwrap()), "{}", switch);
+        }
+    }
+
+    #[test]
+    fn options_with_values() {
+        let cli_args = parse(&["--input", "main.txt", "--output", "out.c", "--backend", "c", "--output-bytecode", "out.bc", "--color", "never"]).unwrap();
+        assert_eq!(cli_args.output.as_deref(), Some("out.c"));
+        assert_eq!(cli_args.backend, Some(Backend::C));
+        assert_eq!(cli_args.bytecode_output.as_deref(), Some("out.bc"));
+        assert_eq!(cli_args.color, ColorChoice::Never);
+        assert_eq!(parse(&["--input", "main.txt", "--backend", "js"]).unwrap().backend, Some(Backend::Js));
+        assert_eq!(parse(&["--input", "main.txt", "--backend", "vm"]).unwrap().backend, Some(Backend::Vm));
+        assert_eq!(parse(&["--input", "main.txt", "--color=always"]).unwrap().color, ColorChoice::Always);
+        assert_eq!(parse(&["--run-bytecode", "out.bc"]).unwrap().bytecode_input.as_deref(), Some("out.bc"));
+    }
+
+    #[test]
+    fn files_takes_every_path_up_to_the_next_option() {
+        let cli_args = parse(&["--files", "a.txt", "b.txt", "--check"]).unwrap();
+        assert_eq!(cli_args.files, vec!("a.txt", "b.txt"));
+        assert!(cli_args.check);
+        assert_eq!(parse(&["--files", "--check"]).unwrap_err(), "Expected at least one file after '--files'");
+    }
+
+    #[test]
+    fn missing_input_is_an_error() {
+        assert_eq!(parse(&["--print-ast"]).unwrap_err(), "Missing required argument '--input <FILE>' or '--files <FILE>...'");
+        // These don't need a file to work on.
+        assert!(parse(&["--help"]).is_ok());
+        assert!(parse(&["--repl"]).is_ok());
+    }
+
+    #[test]
+    fn missing_value_after_an_option_is_an_error() {
+        for option in ["--input", "--output", "--backend", "--output-bytecode", "--run-bytecode", "--color"] {
+            assert_eq!(parse(&["--input", "main.txt", option]).unwrap_err(), format!("Expected a value after '{}'", option));
+        }
+    }
+
+    #[test]
+    fn bad_values_are_errors() {
+        assert_eq!(parse(&["--input", "main.txt", "--backend", "wasm"]).unwrap_err(), "Unknown backend 'wasm', expected one of c, js, or vm");
+        assert!(parse(&["--input", "main.txt", "--color=sometimes"]).unwrap_err().starts_with("Unknown color choice 'sometimes'"));
+        assert_eq!(parse(&["--input", "main.txt", "--verbose"]).unwrap_err(), "Unknown argument '--verbose'");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::AST::*;
+
+#[derive(Debug, Clone)]
+pub struct CodegenError {
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl CodegenError {
+    pub fn new(message: String, location: ASTLocation) -> Self {
+        CodegenError {
+            message,
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+// Names can have '-' in them, which isn't allowed in C.
+fn c_identifier(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn c_operator(name: &str) -> Option<&'static str> {
+    match name {
+        "+" => Some("+"),
+        "-" => Some("-"),
+        "*" => Some("*"),
+        "/" => Some("/"),
+        "%" => Some("%"),
+        "==" => Some("=="),
+        "!=" => Some("!="),
+        "<" => Some("<"),
+        ">" => Some(">"),
+        "<=" => Some("<="),
+        ">=" => Some(">="),
+        _ => None,
+    }
+}
+
+/// Turns the AST into C99 source. Every value is an int64_t, and only top-level 'let name = (...) { ... }' definitions can be functions.
+#[derive(Debug, Default)]
+pub struct CGenerator {
+    // Prototypes go before every definition, so functions can call each other in any order.
+    prototypes: Vec<String>,
+    functions: Vec<String>,
+}
+
+impl CGenerator {
+    pub fn new() -> Self {
+        CGenerator::default()
+    }
+
+    pub fn generate(mut self, ast: &[ASTNode]) -> Result<String, CodegenError> {
+        let mut main_body = String::new();
+        for node in ast {
+            match &node.expression {
+                ASTExpression::ASTInitialization(name, value) | ASTExpression::ASTLetRec(name, value) if matches!(value.expression, ASTExpression::ASTFunction(_, _)) => {
+                    self.generate_function(name, value)?;
+                },
+                ASTExpression::ASTDocComment(_) | ASTExpression::ASTImport { .. } => {},
+                _ => main_body += &self.generate_statement(node, 1)?,
+            }
+        }
+        let mut output = String::from("#include <stdint.h>\n\n");
+        for prototype in &self.prototypes {
+            output += prototype;
+        }
+        if !self.prototypes.is_empty() {
+            output += "\n";
+        }
+        for function in &self.functions {
+            output += function;
+            output += "\n";
+        }
+        output += "int main(void) {\n";
+        output += &main_body;
+        output += "    return 0;\n}\n";
+        Ok(output)
+    }
+
+    // Curried functions are flattened out into a regular C function, so '(x y) { ... }' takes both parameters at once.
+    fn generate_function(&mut self, name: &str, function: &ASTNode) -> Result<(), CodegenError> {
+        let (parameters, body) = match &function.expression {
+            ASTExpression::ASTFunction(parameters, body) => (parameters, body),
+            _ => return Err(CodegenError::new(String::from("Expected a function"), function.location)),
+        };
+        let parameter_names = match &parameters.expression {
+            ASTExpression::ASTParentheses(nodes) => nodes.iter().map(|node| match &node.expression {
+                ASTExpression::ASTVariableRef(name) => Ok(format!("int64_t {}", c_identifier(name))),
+                _ => Err(CodegenError::new(String::from("Function parameters must be variable names"), node.location)),
+            }).collect::<Result<Vec<String>, CodegenError>>()?,
+            _ => return Err(CodegenError::new(String::from("Expected a parameter list"), parameters.location)),
+        };
+        let parameter_list = if parameter_names.is_empty() { String::from("void") } else { parameter_names.join(", ") };
+        let statements: Vec<&ASTNode> = match &body.expression {
+            ASTExpression::ASTScope(nodes) => nodes.iter().collect(),
+            _ => vec!(&**body),
+        };
+        let signature = format!("int64_t {}({})", c_identifier(name), parameter_list);
+        self.prototypes.push(format!("{};\n", signature));
+        let mut output = format!("{} {{\n", signature);
+        output += &self.generate_returning_block(&statements, 1)?;
+        output += "}\n";
+        self.functions.push(output);
+        Ok(())
+    }
+
+    // Generates the statements of a function body, where the last expression becomes the return value.
+    fn generate_returning_block(&mut self, statements: &[&ASTNode], indent: usize) -> Result<String, CodegenError> {
+        let padding = "    ".repeat(indent);
+        let mut output = String::new();
+        match statements.split_last() {
+            None => output += &format!("{}return 0;\n", padding),
+            Some((last, rest)) => {
+                for statement in rest {
+                    output += &self.generate_statement(statement, indent)?;
+                }
+                match &last.expression {
+                    ASTExpression::ASTInitialization(name, _) | ASTExpression::ASTAssignment(name, _) => {
+                        output += &self.generate_statement(last, indent)?;
+                        output += &format!("{}return {};\n", padding, c_identifier(name));
+                    },
+                    ASTExpression::ASTScope(nodes) => {
+                        let nodes: Vec<&ASTNode> = nodes.iter().collect();
+                        output += &format!("{}{{\n", padding);
+                        output += &self.generate_returning_block(&nodes, indent + 1)?;
+                        output += &format!("{}}}\n", padding);
+                    },
+                    ASTExpression::ASTReturn(_) => output += &self.generate_statement(last, indent)?,
+                    _ => output += &format!("{}return {};\n", padding, self.generate_expression(last)?),
+                }
+            },
+        }
+        Ok(output)
+    }
+
+    fn generate_statement(&mut self, node: &ASTNode, indent: usize) -> Result<String, CodegenError> {
+        let padding = "    ".repeat(indent);
+        let output = match &node.expression {
+            ASTExpression::ASTInitialization(name, value) => format!("{}int64_t {} = {};\n", padding, c_identifier(name), self.generate_expression(value)?),
+            ASTExpression::ASTScope(nodes) => {
+                let mut output = format!("{}{{\n", padding);
+                for child in nodes {
+                    output += &self.generate_statement(child, indent + 1)?;
+                }
+                output + &format!("{}}}\n", padding)
+            },
+            ASTExpression::ASTWhile(condition, body) => {
+                format!("{}while ({})\n{}", padding, self.generate_expression(condition)?, self.generate_statement(body, indent)?)
+            },
+            // C has no lists, so only loops over ranges can be turned into C.
+            ASTExpression::ASTForIn(name, iterable, body) => match &iterable.expression {
+                ASTExpression::ASTRange { start, end, inclusive } => {
+                    let name = c_identifier(name);
+                    let comparison = if *inclusive { "<=" } else { "<" };
+                    format!("{}for (int64_t {} = {}; {} {} {}; {}++)\n{}", padding, name, self.generate_expression(start)?, name, comparison, self.generate_expression(end)?, name, self.generate_statement(body, indent)?)
+                },
+                _ => return Err(CodegenError::new(String::from("Only ranges can be looped over in C"), iterable.location)),
+            },
+            ASTExpression::ASTReturn(value) => format!("{}return {};\n", padding, self.generate_expression(value)?),
+            ASTExpression::ASTBreak => format!("{}break;\n", padding),
+            ASTExpression::ASTContinue => format!("{}continue;\n", padding),
+            ASTExpression::ASTDocComment(_) => String::new(),
+            _ => format!("{}{};\n", padding, self.generate_expression(node)?),
+        };
+        Ok(output)
+    }
+
+    fn generate_expression(&mut self, node: &ASTNode) -> Result<String, CodegenError> {
+        let output = match &node.expression {
+            ASTExpression::ASTInteger(x) => format!("(int64_t){}", x),
+            ASTExpression::ASTChar(x) if x.is_ascii() => format!("(int64_t){}", *x as u32),
+            ASTExpression::ASTBool(x) => format!("(int64_t){}", *x as i64),
+            ASTExpression::ASTVariableRef(name) => {
+                if c_operator(name).is_some() {
+                    return Err(CodegenError::new(format!("The operator '{}' can only be used by calling it", name), node.location));
+                }
+                c_identifier(name)
+            },
+            ASTExpression::ASTAssignment(name, value) => format!("({} = {})", c_identifier(name), self.generate_expression(value)?),
+            ASTExpression::ASTBinaryOp(left, operator, right) => {
+                let operator = match operator.as_str() {
+                    "&&" => "&&",
+                    "||" => "||",
+                    operator => c_operator(operator).ok_or(CodegenError::new(format!("The '{}' operator can't be turned into C yet", operator), node.location))?,
+                };
+                format!("({} {} {})", self.generate_expression(left)?, operator, self.generate_expression(right)?)
+            },
+            ASTExpression::ASTUnaryOp(operator, operand) => format!("({}{})", operator, self.generate_expression(operand)?),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+                format!("({} ? {} : {})", self.generate_expression(condition)?, self.generate_expression(then_branch)?, self.generate_expression(else_branch)?)
+            },
+            ASTExpression::ASTParentheses(nodes) => match nodes.split_first() {
+                None => String::from("0"),
+                Some((only, [])) => format!("({})", self.generate_expression(only)?),
+                Some((function, arguments)) => {
+                    let operator = match &function.expression {
+                        ASTExpression::ASTVariableRef(name) => c_operator(name),
+                        _ => None,
+                    };
+                    match operator {
+                        Some(operator) if arguments.len() == 2 => {
+                            format!("({} {} {})", self.generate_expression(&arguments[0])?, operator, self.generate_expression(&arguments[1])?)
+                        },
+                        Some(_) => return Err(CodegenError::new(String::from("Operators take exactly 2 arguments"), node.location)),
+                        None => {
+                            let arguments = arguments.iter().map(|argument| self.generate_expression(argument)).collect::<Result<Vec<String>, CodegenError>>()?;
+                            format!("{}({})", self.generate_expression(function)?, arguments.join(", "))
+                        },
+                    }
+                },
+            },
+            _ => return Err(CodegenError::new(String::from("This expression can't be turned into C yet"), node.location)),
+        };
+        Ok(output)
+    }
+}
+
+/// Generates C source for a whole file
+pub fn generate(ast: &[ASTNode]) -> Result<String, CodegenError> {
+    CGenerator::new().generate(ast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transpile(text: &str) -> String {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        generate(&result.nodes).unwrap()
+    }
+
+    #[test]
+    fn function_becomes_a_c_function() {
+        let output = transpile("let add = (x y) { (+ x y) }");
+        assert!(output.contains("int64_t add(int64_t x, int64_t y)"), "{}", output);
+        assert!(output.contains("return (x + y);"), "{}", output);
+    }
+}
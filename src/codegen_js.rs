@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use crate::AST::*;
+use crate::visitor::walk_depth_first;
+
+// Names can have '-' in them, which isn't allowed in JavaScript.
+fn js_identifier(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn js_operator(name: &str) -> Option<&'static str> {
+    match name {
+        "+" => Some("+"),
+        "-" => Some("-"),
+        "*" => Some("*"),
+        "/" => Some("/"),
+        "%" => Some("%"),
+        "**" => Some("**"),
+        "==" => Some("==="),
+        "!=" => Some("!=="),
+        "<" => Some("<"),
+        ">" => Some(">"),
+        "<=" => Some("<="),
+        ">=" => Some(">="),
+        _ => None,
+    }
+}
+
+fn js_string(x: &str) -> String {
+    format!("{:?}", x)
+}
+
+// Finds every name that gets assigned to after it is declared, since those can't be declared with 'const'.
+fn collect_assigned_names(node: &ASTNode, names: &mut HashSet<String>) {
+    walk_depth_first(node, &mut |node| {
+        if let ASTExpression::ASTAssignment(name, _) = &node.expression {
+            names.insert(name.clone());
+        }
+    });
+}
+
+/// Turns the AST into ES2020 JavaScript
+#[derive(Debug, Default)]
+pub struct JsGenerator {
+    // When set, '(x y) { ... }' becomes '(x, y) => ...' and calls pass all their arguments at once, instead of currying.
+    pub uncurry_functions: bool,
+    assigned_names: HashSet<String>,
+}
+
+impl JsGenerator {
+    pub fn new() -> Self {
+        JsGenerator::default()
+    }
+
+    pub fn with_uncurried_functions() -> Self {
+        JsGenerator { uncurry_functions: true, ..JsGenerator::default() }
+    }
+
+    pub fn generate(mut self, ast: &[ASTNode]) -> String {
+        for node in ast {
+            collect_assigned_names(node, &mut self.assigned_names);
+        }
+        ast.iter().map(|node| self.generate_statement(node) + "\n").collect()
+    }
+
+    fn generate_statement(&self, node: &ASTNode) -> String {
+        match &node.expression {
+            ASTExpression::ASTInitialization(name, value) | ASTExpression::ASTLetRec(name, value) => {
+                let keyword = if self.assigned_names.contains(name) { "let" } else { "const" };
+                format!("{} {} = {};", keyword, js_identifier(name), self.generate_expression(value))
+            },
+            ASTExpression::ASTReturn(value) => format!("return {};", self.generate_expression(value)),
+            ASTExpression::ASTBreak => String::from("break;"),
+            ASTExpression::ASTContinue => String::from("continue;"),
+            ASTExpression::ASTDocComment(text) => text.lines().map(|line| format!("// {}", line)).collect::<Vec<String>>().join("\n"),
+            ASTExpression::ASTImport { path, alias } => match alias {
+                Some(alias) => format!("// import {} as {}", path.join("::"), alias),
+                None => format!("// import {}", path.join("::")),
+            },
+            _ => format!("{};", self.generate_expression(node)),
+        }
+    }
+
+    // A scope is an expression, so it becomes a function that is called right away and returns its last value.
+    // That means a 'return' only leaves the right function when the scope is a function's body, so it is turned into JavaScript nowhere else.
+    fn generate_scope(&self, nodes: &[ASTNode], function_body: bool) -> String {
+        let mut statements: Vec<String> = vec!();
+        match nodes.split_last() {
+            None => statements.push(String::from("return null;")),
+            Some((last, rest)) => {
+                for node in rest {
+                    statements.push(match &node.expression {
+                        ASTExpression::ASTReturn(_) if !function_body => format!("{};", self.generate_expression(node)),
+                        _ => self.generate_statement(node),
+                    });
+                }
+                match &last.expression {
+                    ASTExpression::ASTInitialization(name, _) | ASTExpression::ASTLetRec(name, _) => {
+                        statements.push(self.generate_statement(last));
+                        statements.push(format!("return {};", js_identifier(name)));
+                    },
+                    ASTExpression::ASTReturn(_) if function_body => statements.push(self.generate_statement(last)),
+                    _ => statements.push(format!("return {};", self.generate_expression(last))),
+                }
+            },
+        }
+        format!("(() => {{ {} }})()", statements.join(" "))
+    }
+
+    // The value of a loop's body is thrown away, so scopes and ifs in it become JavaScript blocks and if statements instead of functions.
+    // That keeps 'break' and 'continue' inside of the loop they belong to. The loop itself is still a function, so 'return' is left out.
+    fn generate_loop_body(&self, body: &ASTNode) -> String {
+        match &body.expression {
+            ASTExpression::ASTScope(nodes) => format!("{{ {} }}", nodes.iter().map(|node| self.generate_loop_body(node)).collect::<Vec<String>>().join(" ")),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+                format!("if ({}) {} else {}", self.generate_expression(condition), self.generate_loop_body(then_branch), self.generate_loop_body(else_branch))
+            },
+            ASTExpression::ASTReturn(_) => format!("{};", self.generate_expression(body)),
+            _ => self.generate_statement(body),
+        }
+    }
+
+    fn generate_function(&self, parameters: &ASTNode, body: &ASTNode) -> String {
+        let parameter_names: Vec<String> = match &parameters.expression {
+            ASTExpression::ASTParentheses(nodes) => nodes.iter().map(|node| match &node.expression {
+                ASTExpression::ASTVariableRef(name) => js_identifier(name),
+                _ => String::from("_"),
+            }).collect(),
+            _ => vec!(),
+        };
+        let body = match &body.expression {
+            ASTExpression::ASTScope(nodes) => self.generate_scope(nodes, true),
+            _ => self.generate_expression(body),
+        };
+        if parameter_names.is_empty() {
+            format!("(() => {})", body)
+        } else if self.uncurry_functions {
+            format!("(({}) => {})", parameter_names.join(", "), body)
+        } else {
+            format!("({} => {})", parameter_names.join(" => "), body)
+        }
+    }
+
+    // A match becomes a function that is called right away with the value being matched on.
+    // Names can't have a '$' in them, so the parameter can't hide a variable that an arm uses.
+    fn generate_match(&self, value: &ASTNode, arms: &[(ASTNode, ASTNode)]) -> String {
+        let mut statements: Vec<String> = vec!();
+        for (pattern, body) in arms {
+            let body = self.generate_expression(body);
+            statements.push(match &pattern.expression {
+                ASTExpression::ASTWildcard => format!("return {};", body),
+                ASTExpression::ASTVariableRef(name) => format!("{{ const {} = $match; return {}; }}", js_identifier(name), body),
+                _ => format!("if ($match === {}) return {};", self.generate_expression(pattern), body),
+            });
+        }
+        statements.push(String::from("throw new Error(\"No match arm matched \" + $match);"));
+        format!("(($match) => {{ {} }})({})", statements.join(" "), self.generate_expression(value))
+    }
+
+    fn generate_expression(&self, node: &ASTNode) -> String {
+        match &node.expression {
+            ASTExpression::ASTInteger(x) => x.to_string(),
+            ASTExpression::ASTFloat(x) => format!("{:?}", x),
+            ASTExpression::ASTFloat32(x) => format!("{:?}", x),
+            ASTExpression::ASTString(x) => js_string(x),
+            ASTExpression::ASTChar(x) => js_string(&x.to_string()),
+            ASTExpression::ASTBool(x) => x.to_string(),
+            ASTExpression::ASTNull | ASTExpression::ASTUnit | ASTExpression::ASTWildcard => String::from("null"),
+            ASTExpression::ASTVariableRef(name) => match js_operator(name) {
+                Some(operator) if self.uncurry_functions => format!("((a, b) => a {} b)", operator),
+                Some(operator) => format!("(a => b => a {} b)", operator),
+                None => js_identifier(name),
+            },
+            ASTExpression::ASTInterpolation(nodes) => {
+                let parts: String = nodes.iter().map(|part| match &part.expression {
+                    ASTExpression::ASTString(x) => x.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${"),
+                    _ => format!("${{{}}}", self.generate_expression(part)),
+                }).collect();
+                format!("`{}`", parts)
+            },
+            ASTExpression::ASTAssignment(name, value) => format!("({} = {})", js_identifier(name), self.generate_expression(value)),
+            ASTExpression::ASTInitialization(_, _) | ASTExpression::ASTLetRec(_, _) => self.generate_scope(std::slice::from_ref(node), false),
+            ASTExpression::ASTScope(nodes) => self.generate_scope(nodes, false),
+            ASTExpression::ASTFunction(parameters, body) => self.generate_function(parameters, body),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => format!("({} ? {} : {})", self.generate_expression(condition), self.generate_expression(then_branch), self.generate_expression(else_branch)),
+            ASTExpression::ASTBinaryOp(left, operator, right) => {
+                let operator = match operator.as_str() {
+                    "&&" => "&&",
+                    "||" => "||",
+                    operator => js_operator(operator).unwrap_or(operator),
+                };
+                format!("({} {} {})", self.generate_expression(left), operator, self.generate_expression(right))
+            },
+            ASTExpression::ASTUnaryOp(operator, operand) => format!("({}{})", operator, self.generate_expression(operand)),
+            ASTExpression::ASTFieldAccess(target, field) => format!("{}.{}", self.generate_expression(target), js_identifier(field)),
+            ASTExpression::ASTIndex(target, index) => format!("{}[{}]", self.generate_expression(target), self.generate_expression(index)),
+            ASTExpression::ASTMatch(value, arms) => self.generate_match(value, arms),
+            ASTExpression::ASTWhile(condition, body) => format!("(() => {{ while ({}) {} return null; }})()", self.generate_expression(condition), self.generate_loop_body(body)),
+            ASTExpression::ASTForIn(name, iterable, body) => format!("(() => {{ for (const {} of {}) {} return null; }})()", js_identifier(name), self.generate_expression(iterable), self.generate_loop_body(body)),
+            ASTExpression::ASTTuple(nodes) | ASTExpression::ASTList(nodes) => format!("[{}]", nodes.iter().map(|child| self.generate_expression(child)).collect::<Vec<String>>().join(", ")),
+            ASTExpression::ASTRange { start, end, inclusive } => {
+                let length = if *inclusive { "end - start + 1" } else { "end - start" };
+                format!("((start, end) => Array.from({{ length: Math.max({}, 0) }}, (_, i) => start + i))({}, {})", length, self.generate_expression(start), self.generate_expression(end))
+            },
+            ASTExpression::ASTParentheses(nodes) => match nodes.split_first() {
+                None => String::from("null"),
+                Some((only, [])) => format!("({})", self.generate_expression(only)),
+                Some((function, arguments)) => {
+                    let operator = match &function.expression {
+                        ASTExpression::ASTVariableRef(name) => js_operator(name),
+                        _ => None,
+                    };
+                    let arguments: Vec<String> = arguments.iter().map(|argument| self.generate_expression(argument)).collect();
+                    match operator {
+                        Some(operator) if arguments.len() == 2 => format!("({} {} {})", arguments[0], operator, arguments[1]),
+                        _ if self.uncurry_functions => format!("{}({})", self.generate_expression(function), arguments.join(", ")),
+                        _ => format!("{}({})", self.generate_expression(function), arguments.join(")(")),
+                    }
+                },
+            },
+            ASTExpression::ASTDocComment(_) | ASTExpression::ASTImport { .. } => String::from("undefined"),
+            ASTExpression::ASTReturn(_) => String::from("undefined /* 'return' can only be turned into JavaScript directly inside of a function's body */"),
+            ASTExpression::ASTBreak | ASTExpression::ASTContinue => String::from("undefined /* 'break' and 'continue' can only be turned into JavaScript directly inside of a loop's body */"),
+            ASTExpression::ASTModule(name, _) | ASTExpression::ASTTraitDef(name, _) | ASTExpression::ASTStructDef(name, _) | ASTExpression::ASTEnumDef(name, _) | ASTExpression::ASTImpl { type_name: name, .. } => {
+                format!("undefined /* '{}' can't be turned into JavaScript yet */", name)
+            },
+        }
+    }
+}
+
+/// Generates JavaScript for a whole file, with curried functions
+pub fn generate(ast: &[ASTNode]) -> String {
+    JsGenerator::new().generate(ast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<ASTNode> {
+        let ParseFileResult { nodes, errors } = parse_file(text.to_string(), None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        nodes
+    }
+
+    fn generate_uncurried(text: &str) -> String {
+        JsGenerator::with_uncurried_functions().generate(&parse(text))
+    }
+
+    #[test]
+    fn uncurried_functions_take_every_parameter_at_once() {
+        assert_eq!(generate_uncurried("let add = (x y) { (+ x y) }"), "const add = ((x, y) => (() => { return (x + y); })());\n");
+    }
+
+    #[test]
+    fn uncurried_calls_pass_every_argument_at_once() {
+        assert_eq!(generate_uncurried("(add 1 2)"), "add(1, 2);\n");
+        assert_eq!(generate(&parse("(add 1 2)")), "add(1)(2);\n");
+    }
+
+    #[test]
+    fn reassigned_names_are_declared_with_let() {
+        assert_eq!(generate(&parse("let x = 1\n{ x = 2 }\nlet y = 3")), "let x = 1;\n(() => { return (x = 2); })();\nconst y = 3;\n");
+    }
+}
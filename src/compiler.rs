@@ -0,0 +1,191 @@
+use std::{collections::HashMap, fmt, fs, path::{Path, PathBuf}};
+use crate::AST::*;
+use crate::parsing::ParseError;
+
+#[derive(Debug, Clone)]
+pub enum CompileError {
+    Io { path: PathBuf, message: String },
+    // The same module name is defined by two different files, either as a 'module Name { ... }' or by the file's own name.
+    DuplicateModule { name: String, first: PathBuf, second: PathBuf },
+    // The files that import each other, in order, with the first file repeated at the end.
+    CircularImport(Vec<PathBuf>),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Io { path, message } => write!(f, "{}: {}", path.display(), message),
+            CompileError::DuplicateModule { name, first, second } => {
+                write!(f, "The module '{}' is defined in both {} and {}", name, first.display(), second.display())
+            },
+            CompileError::CircularImport(cycle) => {
+                let cycle: Vec<String> = cycle.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "Circular import: {}", cycle.join(" -> "))
+            },
+        }
+    }
+}
+
+// A file can be imported by its name without the extension, so 'import math' refers to 'math.lang'.
+fn file_module_name(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Parses several files and links them together into a single program
+#[derive(Debug, Default)]
+pub struct Compiler {
+    files: Vec<(PathBuf, Vec<ASTNode>)>,
+    errors: Vec<ParseError>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler::default()
+    }
+
+    /// Parses a file and adds it to the program. Parse errors don't stop the file from being added, and are collected in errors().
+    pub fn add_file(&mut self, path: &Path) -> Result<(), CompileError> {
+        let text = fs::read_to_string(path).map_err(|err| CompileError::Io { path: path.to_path_buf(), message: err.to_string() })?;
+        let ParseFileResult { nodes, errors } = parse_file(text, Some(path.display().to_string()));
+        self.errors.extend(errors);
+        self.files.push((path.to_path_buf(), nodes));
+        Ok(())
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    // Maps every module name to the index of the file that defines it.
+    fn module_owners(&self) -> Result<HashMap<String, usize>, CompileError> {
+        let mut owners: HashMap<String, usize> = HashMap::new();
+        for (index, (path, nodes)) in self.files.iter().enumerate() {
+            let mut names = vec!(file_module_name(path));
+            for node in nodes {
+                if let ASTExpression::ASTModule(name, _) = &node.expression {
+                    names.push(name.clone());
+                }
+            }
+            for name in names {
+                match owners.get(&name) {
+                    Some(&owner) if owner != index => {
+                        return Err(CompileError::DuplicateModule { name, first: self.files[owner].0.clone(), second: path.clone() });
+                    },
+                    _ => {
+                        owners.insert(name, index);
+                    },
+                }
+            }
+        }
+        Ok(owners)
+    }
+
+    // Finds the other files that a file imports, going by the first part of each import's path.
+    fn imported_files(&self, index: usize, owners: &HashMap<String, usize>) -> Vec<usize> {
+        self.files[index].1.iter()
+            .filter_map(|node| match &node.expression {
+                ASTExpression::ASTImport { path, .. } => path.first().and_then(|name| owners.get(name)).copied(),
+                _ => None,
+            })
+            .filter(|imported| *imported != index)
+            .collect()
+    }
+
+    // Adds a file to the order after everything it imports. The stack holds the files that are still being visited, which is how a cycle is found.
+    fn visit(&self, index: usize, owners: &HashMap<String, usize>, visited: &mut Vec<bool>, stack: &mut Vec<usize>, order: &mut Vec<usize>) -> Result<(), CompileError> {
+        if let Some(position) = stack.iter().position(|visiting| *visiting == index) {
+            let cycle = stack[position..].iter().chain([&index]).map(|file| self.files[*file].0.clone()).collect();
+            return Err(CompileError::CircularImport(cycle));
+        }
+        if visited[index] {
+            return Ok(());
+        }
+        stack.push(index);
+        for imported in self.imported_files(index, owners) {
+            self.visit(imported, owners, visited, stack, order)?;
+        }
+        stack.pop();
+        visited[index] = true;
+        order.push(index);
+        Ok(())
+    }
+
+    /// Merges the top-level nodes of every file into one program.
+    /// Files come after the files they import, and otherwise stay in the order they were added.
+    pub fn link(self) -> Result<Vec<ASTNode>, CompileError> {
+        let owners = self.module_owners()?;
+        let mut visited = vec!(false; self.files.len());
+        let mut order = vec!();
+        for index in 0..self.files.len() {
+            self.visit(index, &owners, &mut visited, &mut vec!(), &mut order)?;
+        }
+        let mut files: Vec<Option<Vec<ASTNode>>> = self.files.into_iter().map(|(_, nodes)| Some(nodes)).collect();
+        Ok(order.into_iter().flat_map(|index| files[index].take().unwrap_or_default()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval;
+
+    // Writes the files into their own directory under the system temp directory, and links them in the order given.
+    fn link_files(test_name: &str, files: &[(&str, &str)]) -> (PathBuf, Result<Vec<ASTNode>, CompileError>) {
+        let directory = std::env::temp_dir().join(format!("language_compiler_{}_{}", test_name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mut compiler = Compiler::new();
+        for (name, text) in files {
+            let path = directory.join(name);
+            fs::write(&path, text).unwrap();
+            compiler.add_file(&path).unwrap();
+        }
+        assert!(compiler.errors().is_empty(), "unexpected parse errors: {:?}", compiler.errors());
+        let result = compiler.link();
+        fs::remove_dir_all(&directory).unwrap();
+        (directory, result)
+    }
+
+    #[test]
+    fn files_can_use_each_others_variables() {
+        let (_, result) = link_files("cross_file", &[
+            ("main.lang", "import math\nlet scale = 2\n(double 21)"),
+            ("math.lang", "let double = (x) { (* x scale) }"),
+        ]);
+        let program = result.unwrap();
+        // The imported file comes first, even though it was added second.
+        assert!(program[0].to_string().starts_with("let double"), "{}", program[0]);
+        let value = eval::eval_ast(&program, &mut eval::Env::new()).unwrap();
+        assert!(matches!(value, eval::Value::Int(42)));
+    }
+
+    #[test]
+    fn module_defined_twice_is_an_error() {
+        let (directory, result) = link_files("duplicate_module", &[
+            ("a.lang", "module shapes { let sides = 3 }"),
+            ("shapes.lang", "let sides = 4"),
+        ]);
+        match result {
+            Err(CompileError::DuplicateModule { name, first, second }) => {
+                assert_eq!(name, "shapes");
+                assert_eq!((first, second), (directory.join("a.lang"), directory.join("shapes.lang")));
+            },
+            other => panic!("expected a duplicate module error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_importing_each_other_is_an_error() {
+        let (directory, result) = link_files("circular_import", &[
+            ("a.lang", "import b\nlet x = 1"),
+            ("b.lang", "import c\nlet y = 2"),
+            ("c.lang", "import a\nlet z = 3"),
+        ]);
+        match result {
+            Err(CompileError::CircularImport(cycle)) => {
+                let expected: Vec<PathBuf> = ["a.lang", "b.lang", "c.lang", "a.lang"].iter().map(|name| directory.join(name)).collect();
+                assert_eq!(cycle, expected);
+            },
+            other => panic!("expected a circular import error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::env;
+use crate::json::JsonValue;
+use crate::parsing::{ParseError, ParseInput};
+use crate::resolve::Warning;
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+// Every parse error shares one code for now, until the parser has more specific kinds of errors.
+const PARSE_ERROR_CODE: &str = "E0001";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+// Removes every '\x1b[...m' sequence from the text.
+fn strip_ansi(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+fn render_with_severity(message: &str, line: u32, column: u32, severity: Severity, source: &str) -> String {
+    let window = ParseInput::new(source.to_string()).get_context_window(line, column, 2);
+    // The caret line is the only one without a line number in its gutter.
+    let window: Vec<String> = window.lines().map(|window_line| {
+        if window_line.trim_start().starts_with('|') {
+            window_line.replacen('^', &format!("{}^{}", GREEN, RESET), 1)
+        } else {
+            window_line.to_string()
+        }
+    }).collect();
+    let output = format!("{}{}{}\n{}", severity.color(), message, RESET, window.join("\n"));
+    if env::var_os("NO_COLOR").is_some() {
+        strip_ansi(&output)
+    } else {
+        output
+    }
+}
+
+/// Renders an error in red with the surrounding source, with the '^' under the error in green.
+/// When the NO_COLOR environment variable is set, the output has no color codes at all.
+pub fn render_colored(diagnostic: &ParseError, source: &str) -> String {
+    render_with_severity(&diagnostic.to_string(), diagnostic.line, diagnostic.column, Severity::Error, source)
+}
+
+/// Renders a warning in yellow with the surrounding source, the same way render_colored does for errors
+pub fn render_warning(warning: &Warning, source: &str) -> String {
+    let location = warning.location();
+    render_with_severity(&warning.to_string(), location.line, location.column, Severity::Warning, source)
+}
+
+/// Renders errors as a JSON array for editors and other tools, with one object per error like
+/// '{ "severity": "error", "message": "...", "line": 3, "column": 7, "code": "E0001" }'
+pub fn render_json(diagnostics: &[ParseError]) -> String {
+    JsonValue::Array(diagnostics.iter().map(|diagnostic| JsonValue::Object(vec!(
+        (String::from("severity"), JsonValue::String(Severity::Error.name().to_string())),
+        (String::from("message"), JsonValue::String(diagnostic.message.clone())),
+        (String::from("line"), JsonValue::Number(diagnostic.line.to_string())),
+        (String::from("column"), JsonValue::Number(diagnostic.column.to_string())),
+        (String::from("code"), JsonValue::String(PARSE_ERROR_CODE.to_string())),
+    ))).collect()).to_pretty_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AST::{parse_file, ASTLocation};
+
+    // NO_COLOR is shared by the whole process, so both cases are checked in one test instead of two that could run at the same time.
+    #[test]
+    fn no_color_strips_every_escape_code() {
+        let error = ParseError::new(String::from("Expected ')'"), 1, 3);
+        env::remove_var("NO_COLOR");
+        let colored = render_colored(&error, "(+ 1");
+        assert!(colored.starts_with(&format!("{}Expected ')'{}", RED, RESET)), "{:?}", colored);
+        assert!(colored.contains(&format!("{}^{}", GREEN, RESET)));
+        let warning = render_warning(&Warning::UnusedVariable { name: String::from("x"), location: ASTLocation { line: 1, column: 5 } }, "let x = 1");
+        assert!(warning.starts_with(&format!("{}1:5: warning: 'x' is never used{}", YELLOW, RESET)), "{:?}", warning);
+        env::set_var("NO_COLOR", "1");
+        let plain = render_colored(&error, "(+ 1");
+        env::remove_var("NO_COLOR");
+        assert!(!plain.contains('\x1b'), "{:?}", plain);
+        assert_eq!(plain, "Expected ')'\n1 | (+ 1\n  |   ^");
+    }
+
+    #[test]
+    fn json_errors_have_a_line_and_a_message() {
+        let result = parse_file(String::from("let x = 1\nlet y = )"), None);
+        let json = render_json(&result.errors);
+        assert!(json.starts_with("[\n  {\n    \"severity\": \"error\",\n    \"message\": \""), "{}", json);
+        assert!(json.contains("\"line\": 2,"), "{}", json);
+        assert!(json.contains("\"column\": "), "{}", json);
+        assert!(json.contains("\"code\": \"E0001\""), "{}", json);
+        assert_eq!(render_json(&[]), "[]");
+    }
+}
@@ -0,0 +1,601 @@
+use std::{rc::Rc, cell::RefCell, collections::HashMap, fmt};
+use crate::AST::*;
+
+/// Operators that are built into the interpreter instead of being defined in the language, like '(+ 1 2)'
+pub const BUILTIN_OPERATORS: &[&str] = &["+", "-", "*", "/", "%", "**", "==", "!=", "<", ">", "<=", ">="];
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Char(char),
+    Bool(bool),
+    // Functions take a single parameter, so functions with more parameters are curried.
+    // A function with no parameters has an empty parameter name.
+    Fun(String, Box<ASTNode>, Env),
+    List(Vec<Value>),
+    Unit,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Str(x) => write!(f, "{}", x),
+            Value::Char(x) => write!(f, "{}", x),
+            Value::Bool(x) => write!(f, "{}", x),
+            Value::Fun(parameter, _, _) => write!(f, "<function ({})>", parameter),
+            Value::List(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            },
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
+// Jumps out of the normal flow of evaluation. These travel up as errors until something handles them.
+#[derive(Debug, Clone)]
+pub enum ControlFlow {
+    Return(Value),
+    Break,
+    Continue,
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+    // Set when this isn't really an error, but a jump like 'return'. The message is used if nothing handles the jump.
+    pub control_flow: Option<ControlFlow>,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, location: ASTLocation) -> Self {
+        RuntimeError {
+            message,
+            line: location.line,
+            column: location.column,
+            control_flow: None,
+        }
+    }
+
+    pub fn control_flow(control_flow: ControlFlow, message: String, location: ASTLocation) -> Self {
+        RuntimeError { control_flow: Some(control_flow), ..RuntimeError::new(message, location) }
+    }
+}
+
+/// A chain of variable frames. Cloning an Env shares its frames, which is how closures see variables defined after them (including themselves for recursion).
+#[derive(Debug, Clone, Default)]
+pub struct Env {
+    vars: Rc<RefCell<HashMap<String, Value>>>,
+    parent: Option<Box<Env>>,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Env::default()
+    }
+
+    /// Creates a new, empty frame whose parent is this environment
+    pub fn child(&self) -> Env {
+        Env {
+            vars: Rc::new(RefCell::new(HashMap::new())),
+            parent: Some(Box::new(self.clone())),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.vars.borrow().get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref().and_then(|parent| parent.get(name)),
+        }
+    }
+
+    /// Adds a variable to the innermost frame
+    pub fn define(&self, name: &str, value: Value) {
+        self.vars.borrow_mut().insert(name.to_string(), value);
+    }
+
+    /// Updates an existing variable in whichever frame it was defined in. Returns false if the variable doesn't exist.
+    pub fn assign(&self, name: &str, value: Value) -> bool {
+        if self.vars.borrow().contains_key(name) {
+            self.vars.borrow_mut().insert(name.to_string(), value);
+            true
+        } else {
+            match &self.parent {
+                Some(parent) => parent.assign(name, value),
+                None => false,
+            }
+        }
+    }
+}
+
+fn apply_builtin(operator: &str, left: Value, right: Value, location: ASTLocation) -> Result<Value, RuntimeError> {
+    let value = match (operator, left, right) {
+        ("/", Value::Int(_), Value::Int(0)) | ("%", Value::Int(_), Value::Int(0)) => {
+            return Err(RuntimeError::new(String::from("Division by zero"), location));
+        },
+        ("+", Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_add(b)),
+        ("-", Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_sub(b)),
+        ("*", Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_mul(b)),
+        ("/", Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_div(b)),
+        ("%", Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_rem(b)),
+        ("**", Value::Int(a), Value::Int(b)) => match u32::try_from(b) {
+            Ok(b) => Value::Int(a.wrapping_pow(b)),
+            Err(_) => return Err(RuntimeError::new(format!("Cannot raise an integer to the power of {}", b), location)),
+        },
+        ("+", Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+        ("-", Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+        ("*", Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+        ("/", Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+        ("%", Value::Float(a), Value::Float(b)) => Value::Float(a % b),
+        ("**", Value::Float(a), Value::Float(b)) => Value::Float(a.powf(b)),
+        ("+", Value::Str(a), Value::Str(b)) => Value::Str(a + &b),
+        ("==", Value::Int(a), Value::Int(b)) => Value::Bool(a == b),
+        ("!=", Value::Int(a), Value::Int(b)) => Value::Bool(a != b),
+        ("<", Value::Int(a), Value::Int(b)) => Value::Bool(a < b),
+        (">", Value::Int(a), Value::Int(b)) => Value::Bool(a > b),
+        ("<=", Value::Int(a), Value::Int(b)) => Value::Bool(a <= b),
+        (">=", Value::Int(a), Value::Int(b)) => Value::Bool(a >= b),
+        ("==", Value::Float(a), Value::Float(b)) => Value::Bool(a == b),
+        ("!=", Value::Float(a), Value::Float(b)) => Value::Bool(a != b),
+        ("<", Value::Float(a), Value::Float(b)) => Value::Bool(a < b),
+        (">", Value::Float(a), Value::Float(b)) => Value::Bool(a > b),
+        ("<=", Value::Float(a), Value::Float(b)) => Value::Bool(a <= b),
+        (">=", Value::Float(a), Value::Float(b)) => Value::Bool(a >= b),
+        ("==", Value::Str(a), Value::Str(b)) => Value::Bool(a == b),
+        ("!=", Value::Str(a), Value::Str(b)) => Value::Bool(a != b),
+        ("==", Value::Char(a), Value::Char(b)) => Value::Bool(a == b),
+        ("!=", Value::Char(a), Value::Char(b)) => Value::Bool(a != b),
+        ("==", Value::Bool(a), Value::Bool(b)) => Value::Bool(a == b),
+        ("!=", Value::Bool(a), Value::Bool(b)) => Value::Bool(a != b),
+        (operator, left, right) => {
+            return Err(RuntimeError::new(format!("Cannot apply '{}' to {} and {}", operator, left, right), location));
+        },
+    };
+    Ok(value)
+}
+
+// What evaluating an expression in tail position gives back.
+// Instead of making a call in tail position right away, the function and its arguments are handed back up to apply, which loops on them.
+// That way, a function calling itself as the last thing it does doesn't grow the Rust call stack.
+enum EvalResult {
+    Value(Value),
+    // The function to call, along with the arguments to apply to it one at a time (since functions are curried).
+    TailCall(Value, Vec<(Value, ASTLocation)>),
+}
+
+/// Calls a function value with a single argument
+pub fn apply(function: Value, argument: Value, location: ASTLocation) -> Result<Value, RuntimeError> {
+    let (mut function, mut argument, mut location) = (function, argument, location);
+    loop {
+        let (body, mut call_env) = match function {
+            Value::Fun(parameter, body, closure_env) => {
+                let call_env = closure_env.child();
+                if !parameter.is_empty() {
+                    call_env.define(&parameter, argument);
+                }
+                (body, call_env)
+            },
+            other => return Err(RuntimeError::new(format!("Cannot call {} as a function", other), location)),
+        };
+        let result = match eval_tail(&body, &mut call_env) {
+            Err(RuntimeError { control_flow: Some(ControlFlow::Return(value)), .. }) => return Ok(value),
+            // A loop can't be left from inside of a function that it calls, so this is a real error now.
+            Err(error @ RuntimeError { control_flow: Some(ControlFlow::Break | ControlFlow::Continue), .. }) => {
+                return Err(RuntimeError { control_flow: None, ..error });
+            },
+            result => result?,
+        };
+        match result {
+            EvalResult::Value(value) => return Ok(value),
+            EvalResult::TailCall(next_function, mut arguments) => {
+                // Only the last argument is the tail call, the ones before it just build up a partially applied function.
+                let (last_argument, last_location) = arguments.pop().unwrap_or((Value::Unit, location));
+                function = next_function;
+                for (argument, location) in arguments {
+                    function = apply(function, argument, location)?;
+                }
+                argument = last_argument;
+                location = last_location;
+            },
+        }
+    }
+}
+
+// Makes any call that was left over from evaluating in tail position.
+fn finish_tail_call(result: EvalResult) -> Result<Value, RuntimeError> {
+    match result {
+        EvalResult::Value(value) => Ok(value),
+        EvalResult::TailCall(mut function, arguments) => {
+            for (argument, location) in arguments {
+                function = apply(function, argument, location)?;
+            }
+            Ok(function)
+        },
+    }
+}
+
+// Builds a curried function value, so '(x y) { ... }' becomes a function of x that returns a function of y.
+fn make_function(parameters: &ASTNode, body: &ASTNode, env: &Env) -> Result<Value, RuntimeError> {
+    let names: Vec<&ASTNode> = match &parameters.expression {
+        ASTExpression::ASTParentheses(nodes) => nodes.iter().collect(),
+        _ => return Err(RuntimeError::new(String::from("Expected a parameter list"), parameters.location)),
+    };
+    let (first, rest) = match names.split_first() {
+        Some(split) => split,
+        None => return Ok(Value::Fun(String::new(), Box::new(body.clone()), env.clone())),
+    };
+    let name = match &first.expression {
+        ASTExpression::ASTVariableRef(name) => name.clone(),
+        _ => return Err(RuntimeError::new(String::from("Function parameters must be variable names"), first.location)),
+    };
+    let inner_body =
+        if rest.is_empty() {
+            body.clone()
+        } else {
+            let rest_parameters = ASTNode::new(ASTExpression::ASTParentheses(rest.iter().map(|node| (*node).clone()).collect()), rest[0].location.line, rest[0].location.column);
+            ASTNode::new(ASTExpression::ASTFunction(Box::new(rest_parameters), Box::new(body.clone())), body.location.line, body.location.column)
+        };
+    Ok(Value::Fun(name, Box::new(inner_body), env.clone()))
+}
+
+// Evaluates a scope where the last expression is in tail position.
+fn eval_in_scope_tail(nodes: &[ASTNode], env: &Env) -> Result<EvalResult, RuntimeError> {
+    let mut scope_env = env.child();
+    match nodes.split_last() {
+        None => Ok(EvalResult::Value(Value::Unit)),
+        Some((last, rest)) => {
+            for node in rest {
+                eval(node, &mut scope_env)?;
+            }
+            eval_tail(last, &mut scope_env)
+        },
+    }
+}
+
+fn eval_in_scope(nodes: &[ASTNode], env: &Env) -> Result<Value, RuntimeError> {
+    finish_tail_call(eval_in_scope_tail(nodes, env)?)
+}
+
+// Evaluates an expression whose value is returned straight from the function it is in.
+// Calls are handed back as a TailCall instead of being made, and everything else is evaluated normally.
+fn eval_tail(node: &ASTNode, env: &mut Env) -> Result<EvalResult, RuntimeError> {
+    match &node.expression {
+        ASTExpression::ASTScope(nodes) => eval_in_scope_tail(nodes, env),
+        // Only the branch that runs is in tail position, so recursion through an if doesn't grow the stack.
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+            if eval_condition(condition, env)? { eval_tail(then_branch, env) } else { eval_tail(else_branch, env) }
+        },
+        ASTExpression::ASTMatch(value, arms) => {
+            let value = eval(value, env)?;
+            let (body, mut arm_env) = select_match_arm(value, arms, env, node.location)?;
+            eval_tail(body, &mut arm_env)
+        },
+        ASTExpression::ASTParentheses(nodes) => match nodes.split_first() {
+            Some((only, [])) => match eval(only, env)? {
+                Value::Fun(parameter, body, closure_env) if parameter.is_empty() => Ok(EvalResult::TailCall(Value::Fun(parameter, body, closure_env), vec!((Value::Unit, node.location)))),
+                value => Ok(EvalResult::Value(value)),
+            },
+            Some((function, arguments)) if !is_builtin_call(function, env) => {
+                let function_value = eval(function, env)?;
+                let mut argument_values = vec!();
+                for argument in arguments {
+                    argument_values.push((eval(argument, env)?, argument.location));
+                }
+                Ok(EvalResult::TailCall(function_value, argument_values))
+            },
+            _ => Ok(EvalResult::Value(eval(node, env)?)),
+        },
+        _ => Ok(EvalResult::Value(eval(node, env)?)),
+    }
+}
+
+// Finds the first arm whose pattern matches the value, and returns its body along with the environment to run it in.
+// The environment is a child of env, which holds the value when the pattern is a name.
+fn select_match_arm<'a>(value: Value, arms: &'a [(ASTNode, ASTNode)], env: &Env, location: ASTLocation) -> Result<(&'a ASTNode, Env), RuntimeError> {
+    for (pattern, body) in arms {
+        let matches = match (&pattern.expression, &value) {
+            (ASTExpression::ASTWildcard, _) | (ASTExpression::ASTVariableRef(_), _) => true,
+            (ASTExpression::ASTInteger(x), Value::Int(y)) => x == y,
+            (ASTExpression::ASTBool(x), Value::Bool(y)) => x == y,
+            _ => false,
+        };
+        if matches {
+            let arm_env = env.child();
+            if let ASTExpression::ASTVariableRef(name) = &pattern.expression {
+                arm_env.define(name, value);
+            }
+            return Ok((body, arm_env));
+        }
+    }
+    Err(RuntimeError::new(format!("No match arm matched {}", value), location))
+}
+
+// Runs one iteration of a loop, giving back whether the loop should keep going.
+fn eval_loop_body(body: &ASTNode, env: &mut Env) -> Result<bool, RuntimeError> {
+    match eval(body, env) {
+        Ok(_) | Err(RuntimeError { control_flow: Some(ControlFlow::Continue), .. }) => Ok(true),
+        Err(RuntimeError { control_flow: Some(ControlFlow::Break), .. }) => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+fn eval_condition(condition: &ASTNode, env: &mut Env) -> Result<bool, RuntimeError> {
+    match eval(condition, env)? {
+        Value::Bool(x) => Ok(x),
+        other => Err(RuntimeError::new(format!("Expected a boolean condition, but found {}", other), condition.location)),
+    }
+}
+
+// Builtin operators are used directly by name, unless the name has been defined as a regular variable.
+fn is_builtin_call(function: &ASTNode, env: &Env) -> bool {
+    match &function.expression {
+        ASTExpression::ASTVariableRef(name) => BUILTIN_OPERATORS.contains(&name.as_str()) && env.get(name).is_none(),
+        _ => false,
+    }
+}
+
+fn eval_range_bounds(start: &ASTNode, end: &ASTNode, env: &mut Env, location: ASTLocation) -> Result<(i64, i64), RuntimeError> {
+    match (eval(start, env)?, eval(end, env)?) {
+        (Value::Int(start), Value::Int(end)) => Ok((start, end)),
+        (start, end) => Err(RuntimeError::new(format!("Range bounds must be integers, but found {} and {}", start, end), location)),
+    }
+}
+
+pub fn eval(node: &ASTNode, env: &mut Env) -> Result<Value, RuntimeError> {
+    let location = node.location;
+    let value = match &node.expression {
+        ASTExpression::ASTInteger(x) => Value::Int(*x),
+        ASTExpression::ASTFloat(x) => Value::Float(*x),
+        ASTExpression::ASTFloat32(x) => Value::Float(*x as f64),
+        ASTExpression::ASTString(x) => Value::Str(x.clone()),
+        ASTExpression::ASTChar(x) => Value::Char(*x),
+        ASTExpression::ASTBool(x) => Value::Bool(*x),
+        ASTExpression::ASTNull | ASTExpression::ASTUnit => Value::Unit,
+        ASTExpression::ASTInterpolation(parts) => {
+            let mut output = String::new();
+            for part in parts {
+                output += &eval(part, env)?.to_string();
+            }
+            Value::Str(output)
+        },
+        ASTExpression::ASTVariableRef(name) => {
+            env.get(name).ok_or(RuntimeError::new(format!("Undefined variable '{}'", name), location))?
+        },
+        ASTExpression::ASTInitialization(name, value) => {
+            let value = eval(value, env)?;
+            env.define(name, value.clone());
+            value
+        },
+        // Closures share their frames with the environment they were created in, so the function can see itself once it is defined.
+        ASTExpression::ASTLetRec(name, value) => {
+            let value = eval(value, env)?;
+            env.define(name, value.clone());
+            value
+        },
+        ASTExpression::ASTAssignment(name, value) => {
+            let value = eval(value, env)?;
+            if !env.assign(name, value.clone()) {
+                return Err(RuntimeError::new(format!("Cannot assign to undefined variable '{}'", name), location));
+            }
+            value
+        },
+        ASTExpression::ASTScope(nodes) => eval_in_scope(nodes, env)?,
+        ASTExpression::ASTFunction(parameters, body) => make_function(parameters, body, env)?,
+        // A single expression in parentheses is just grouping, unless it is a function with no parameters, which gets called.
+        // With more than one expression, the first is applied to the rest.
+        ASTExpression::ASTParentheses(nodes) => match nodes.split_first() {
+            None => Value::Unit,
+            Some((only, [])) => match eval(only, env)? {
+                Value::Fun(parameter, body, closure_env) if parameter.is_empty() => apply(Value::Fun(parameter, body, closure_env), Value::Unit, location)?,
+                value => value,
+            },
+            Some((function, arguments)) => {
+                if let ASTExpression::ASTVariableRef(name) = &function.expression {
+                    if is_builtin_call(function, env) {
+                        let mut values = vec!();
+                        for argument in arguments {
+                            values.push(eval(argument, env)?);
+                        }
+                        return match values.len() {
+                            2 => apply_builtin(name, values[0].clone(), values[1].clone(), location),
+                            count => Err(RuntimeError::new(format!("'{}' takes 2 arguments, but was given {}", name, count), location)),
+                        };
+                    }
+                }
+                let mut function_value = eval(function, env)?;
+                for argument in arguments {
+                    let argument_value = eval(argument, env)?;
+                    function_value = apply(function_value, argument_value, argument.location)?;
+                }
+                function_value
+            },
+        },
+        ASTExpression::ASTTuple(nodes) | ASTExpression::ASTList(nodes) => {
+            let mut values = vec!();
+            for node in nodes {
+                values.push(eval(node, env)?);
+            }
+            Value::List(values)
+        },
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+            if eval_condition(condition, env)? { eval(then_branch, env)? } else { eval(else_branch, env)? }
+        },
+        ASTExpression::ASTWhile(condition, body) => {
+            while eval_condition(condition, env)? {
+                if !eval_loop_body(body, env)? {
+                    break;
+                }
+            }
+            Value::Unit
+        },
+        ASTExpression::ASTBinaryOp(left, operator, right) => match operator.as_str() {
+            // The right side of '&&' and '||' is only evaluated when it can change the result.
+            "&&" => Value::Bool(eval_condition(left, env)? && eval_condition(right, env)?),
+            "||" => Value::Bool(eval_condition(left, env)? || eval_condition(right, env)?),
+            _ => {
+                let left = eval(left, env)?;
+                let right = eval(right, env)?;
+                apply_builtin(operator, left, right, location)?
+            },
+        },
+        // None of the values have fields yet, so reading one is always an error.
+        ASTExpression::ASTFieldAccess(target, field) => {
+            let target = eval(target, env)?;
+            return Err(RuntimeError::new(format!("Cannot access the field '{}' of {}", field, target), location));
+        },
+        ASTExpression::ASTMatch(value, arms) => {
+            let value = eval(value, env)?;
+            let (body, mut arm_env) = select_match_arm(value, arms, env, location)?;
+            eval(body, &mut arm_env)?
+        },
+        ASTExpression::ASTReturn(value) => {
+            let value = eval(value, env)?;
+            return Err(RuntimeError::control_flow(ControlFlow::Return(value), String::from("'return' can only be used inside of a function"), location));
+        },
+        ASTExpression::ASTBreak => return Err(RuntimeError::control_flow(ControlFlow::Break, String::from("'break' can only be used inside of a loop"), location)),
+        ASTExpression::ASTContinue => return Err(RuntimeError::control_flow(ControlFlow::Continue, String::from("'continue' can only be used inside of a loop"), location)),
+        ASTExpression::ASTWildcard => return Err(RuntimeError::new(String::from("'_' can only be used as a pattern"), location)),
+        ASTExpression::ASTIndex(target, index) => {
+            let target = eval(target, env)?;
+            let index = eval(index, env)?;
+            let item = match (&target, &index) {
+                (Value::List(values), Value::Int(i)) => usize::try_from(*i).ok().and_then(|i| values.get(i).cloned()),
+                (Value::Str(x), Value::Int(i)) => usize::try_from(*i).ok().and_then(|i| x.chars().nth(i)).map(Value::Char),
+                _ => return Err(RuntimeError::new(format!("Cannot index {} with {}", target, index), location)),
+            };
+            item.ok_or(RuntimeError::new(format!("Index {} is out of bounds", index), location))?
+        },
+        ASTExpression::ASTUnaryOp(operator, operand) => match (operator.as_str(), eval(operand, env)?) {
+            ("-", Value::Int(x)) => Value::Int(x.wrapping_neg()),
+            ("-", Value::Float(x)) => Value::Float(-x),
+            ("!", Value::Bool(x)) => Value::Bool(!x),
+            (operator, value) => return Err(RuntimeError::new(format!("Cannot apply '{}' to {}", operator, value), location)),
+        },
+        ASTExpression::ASTForIn(name, iterable, body) => {
+            // Looping over a range counts through it directly, so a huge range that is broken out of early never gets built as a list.
+            let items: Box<dyn Iterator<Item = Value>> = match &iterable.expression {
+                ASTExpression::ASTRange { start, end, inclusive } => {
+                    let (start, end) = eval_range_bounds(start, end, env, iterable.location)?;
+                    if *inclusive {
+                        Box::new((start..=end).map(Value::Int))
+                    } else {
+                        Box::new((start..end).map(Value::Int))
+                    }
+                },
+                _ => match eval(iterable, env)? {
+                    Value::List(values) => Box::new(values.into_iter()),
+                    Value::Str(x) => Box::new(x.chars().map(Value::Char).collect::<Vec<Value>>().into_iter()),
+                    other => return Err(RuntimeError::new(format!("Cannot loop over {}", other), iterable.location)),
+                },
+            };
+            for item in items {
+                let mut loop_env = env.child();
+                loop_env.define(name, item);
+                if !eval_loop_body(body, &mut loop_env)? {
+                    break;
+                }
+            }
+            Value::Unit
+        },
+        ASTExpression::ASTRange { start, end, inclusive } => {
+            let (start, end) = eval_range_bounds(start, end, env, location)?;
+            if *inclusive {
+                Value::List((start..=end).map(Value::Int).collect())
+            } else {
+                Value::List((start..end).map(Value::Int).collect())
+            }
+        },
+        ASTExpression::ASTModule(_, body) => {
+            eval_in_scope(body, env)?;
+            Value::Unit
+        },
+        ASTExpression::ASTTraitDef(_, _)
+        | ASTExpression::ASTStructDef(_, _)
+        | ASTExpression::ASTEnumDef(_, _)
+        | ASTExpression::ASTImpl { .. }
+        | ASTExpression::ASTImport { .. }
+        | ASTExpression::ASTDocComment(_) => Value::Unit,
+    };
+    Ok(value)
+}
+
+/// Evaluates every top-level node in order, returning the value of the last one
+pub fn eval_ast(ast: &[ASTNode], env: &mut Env) -> Result<Value, RuntimeError> {
+    let mut last_value = Value::Unit;
+    for node in ast {
+        last_value = eval(node, env)?;
+    }
+    Ok(last_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_source(text: &str) -> Value {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        eval_ast(&result.nodes, &mut Env::new()).unwrap()
+    }
+
+    fn eval_error(text: &str) -> RuntimeError {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        eval_ast(&result.nodes, &mut Env::new()).unwrap_err()
+    }
+
+    #[test]
+    fn calling_a_function_with_two_parameters() {
+        assert!(matches!(eval_source("let add = (x y) { (+ x y) }\n(add 1 2)"), Value::Int(3)));
+    }
+
+    #[test]
+    fn variables_in_a_scope_stay_in_it() {
+        assert!(matches!(eval_source("let x = 1\n{ let x = 2\n x }"), Value::Int(2)));
+        assert!(matches!(eval_source("let x = 1\n{ let x = 2 }\nx"), Value::Int(1)));
+        // Assigning changes the variable where it was defined, even from inside of a scope.
+        assert!(matches!(eval_source("let x = 1\n{ x = 2 }\nx"), Value::Int(2)));
+        let error = eval_error("{ let inner = 1 }\ninner");
+        assert_eq!((error.message.as_str(), error.line, error.column), ("Undefined variable 'inner'", 2, 1));
+    }
+
+    #[test]
+    fn closures_keep_their_environment() {
+        assert!(matches!(eval_source("let make_adder = (x) { (y) { (+ x y) } }\nlet add_two = (make_adder 2)\n(add_two 5)"), Value::Int(7)));
+        // Closures share variables with the scope they were made in, so they see later assignments.
+        assert!(matches!(eval_source("let count = 0\nlet get = () { count }\ncount = 5\n(get)"), Value::Int(5)));
+    }
+
+    #[test]
+    fn runtime_errors_have_locations() {
+        let error = eval_error("let x = 1\n(/ x 0)");
+        assert_eq!((error.message.as_str(), error.line, error.column), ("Division by zero", 2, 1));
+        let error = eval_error("(1 2)");
+        assert_eq!((error.message.as_str(), error.line, error.column), ("Cannot call 1 as a function", 1, 4));
+        assert_eq!(eval_error("(+ 1 \"a\")").message, "Cannot apply '+' to 1 and a");
+        assert_eq!(eval_error("y = 1").message, "Cannot assign to undefined variable 'y'");
+    }
+
+    #[test]
+    fn breaking_out_of_a_huge_range_stops_right_away() {
+        let value = eval_source("let count = 0\nfor i in 0..100000000000 { count = count + 1\n break }\ncount");
+        assert_eq!(value.to_string(), "1");
+    }
+
+    #[test]
+    fn range_used_as_a_value_is_a_list() {
+        assert_eq!(eval_source("1..=3").to_string(), eval_source("[1, 2, 3]").to_string());
+    }
+
+    #[test]
+    fn tail_recursion_does_not_grow_the_stack() {
+        let value = eval_source("letrec loop = (n) { if (== n 0) then 0 else (loop (- n 1)) }\n(loop 1000000)");
+        assert_eq!(value.to_string(), "0");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::AST::*;
+
+// serde isn't available to this crate, so the AST is turned into JSON by hand.
+// The layout follows serde's default externally tagged enums, so 'ASTInteger(42)' becomes '{"ASTInteger": 42}'.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    // Numbers are kept as the text they will be printed as.
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+fn escape_json_string(x: &str) -> String {
+    let mut output = String::from("\"");
+    for c in x.chars() {
+        match c {
+            '"' => output += "\\\"",
+            '\\' => output += "\\\\",
+            '\n' => output += "\\n",
+            '\r' => output += "\\r",
+            '\t' => output += "\\t",
+            c if (c as u32) < 0x20 => output += &format!("\\u{:04x}", c as u32),
+            c => output.push(c),
+        }
+    }
+    output + "\""
+}
+
+impl JsonValue {
+    /// Prints the value with two spaces of indentation per level, like serde_json::to_string_pretty
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::new();
+        self.write_pretty(&mut output, 0);
+        output
+    }
+
+    fn write_pretty(&self, output: &mut String, indent: usize) {
+        let padding = "  ".repeat(indent + 1);
+        match self {
+            JsonValue::Null => *output += "null",
+            JsonValue::Bool(x) => *output += &x.to_string(),
+            JsonValue::Number(x) => *output += x,
+            JsonValue::String(x) => *output += &escape_json_string(x),
+            JsonValue::Array(values) if values.is_empty() => *output += "[]",
+            JsonValue::Array(values) => {
+                *output += "[\n";
+                for (index, value) in values.iter().enumerate() {
+                    *output += &padding;
+                    value.write_pretty(output, indent + 1);
+                    *output += if index + 1 < values.len() { ",\n" } else { "\n" };
+                }
+                *output += &"  ".repeat(indent);
+                *output += "]";
+            },
+            JsonValue::Object(fields) if fields.is_empty() => *output += "{}",
+            JsonValue::Object(fields) => {
+                *output += "{\n";
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    *output += &padding;
+                    *output += &escape_json_string(key);
+                    *output += ": ";
+                    value.write_pretty(output, indent + 1);
+                    *output += if index + 1 < fields.len() { ",\n" } else { "\n" };
+                }
+                *output += &"  ".repeat(indent);
+                *output += "}";
+            },
+        }
+    }
+}
+
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+fn string(x: &str) -> JsonValue {
+    JsonValue::String(x.to_string())
+}
+
+// Non-finite floats can't be written in JSON, so they become null like they do in serde_json.
+fn float(x: f64) -> JsonValue {
+    if x.is_finite() { JsonValue::Number(format!("{:?}", x)) } else { JsonValue::Null }
+}
+
+fn nodes(nodes: &[ASTNode]) -> JsonValue {
+    JsonValue::Array(nodes.iter().map(|node| node.to_json()).collect())
+}
+
+fn optional_string(x: &Option<String>) -> JsonValue {
+    x.as_deref().map(string).unwrap_or(JsonValue::Null)
+}
+
+fn variant(name: &str, value: JsonValue) -> JsonValue {
+    JsonValue::Object(vec!((name.to_string(), value)))
+}
+
+fn fields(fields: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+impl ToJson for ASTLocation {
+    fn to_json(&self) -> JsonValue {
+        fields(vec!(
+            ("line", JsonValue::Number(self.line.to_string())),
+            ("column", JsonValue::Number(self.column.to_string())),
+        ))
+    }
+}
+
+impl ToJson for ASTNode {
+    fn to_json(&self) -> JsonValue {
+        fields(vec!(
+            ("expression", self.expression.to_json()),
+            ("location", self.location.to_json()),
+        ))
+    }
+}
+
+impl ToJson for ASTExpression {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            ASTExpression::ASTVariableRef(name) => variant("ASTVariableRef", string(name)),
+            ASTExpression::ASTInteger(x) => variant("ASTInteger", JsonValue::Number(x.to_string())),
+            ASTExpression::ASTFloat(x) => variant("ASTFloat", float(*x)),
+            ASTExpression::ASTFloat32(x) => variant("ASTFloat32", float(*x as f64)),
+            ASTExpression::ASTString(x) => variant("ASTString", string(x)),
+            ASTExpression::ASTInterpolation(parts) => variant("ASTInterpolation", nodes(parts)),
+            ASTExpression::ASTChar(x) => variant("ASTChar", string(&x.to_string())),
+            ASTExpression::ASTBool(x) => variant("ASTBool", JsonValue::Bool(*x)),
+            ASTExpression::ASTAssignment(name, value) => variant("ASTAssignment", JsonValue::Array(vec!(string(name), value.to_json()))),
+            ASTExpression::ASTInitialization(name, value) => variant("ASTInitialization", JsonValue::Array(vec!(string(name), value.to_json()))),
+            ASTExpression::ASTLetRec(name, value) => variant("ASTLetRec", JsonValue::Array(vec!(string(name), value.to_json()))),
+            ASTExpression::ASTScope(children) => variant("ASTScope", nodes(children)),
+            ASTExpression::ASTParentheses(children) => variant("ASTParentheses", nodes(children)),
+            ASTExpression::ASTTuple(children) => variant("ASTTuple", nodes(children)),
+            ASTExpression::ASTList(children) => variant("ASTList", nodes(children)),
+            ASTExpression::ASTRange { start, end, inclusive } => variant("ASTRange", fields(vec!(
+                ("start", start.to_json()),
+                ("end", end.to_json()),
+                ("inclusive", JsonValue::Bool(*inclusive)),
+            ))),
+            ASTExpression::ASTDocComment(text) => variant("ASTDocComment", string(text)),
+            ASTExpression::ASTFunction(parameters, body) => variant("ASTFunction", JsonValue::Array(vec!(parameters.to_json(), body.to_json()))),
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => variant("ASTIfElse", JsonValue::Array(vec!(condition.to_json(), then_branch.to_json(), else_branch.to_json()))),
+            ASTExpression::ASTWhile(condition, body) => variant("ASTWhile", JsonValue::Array(vec!(condition.to_json(), body.to_json()))),
+            ASTExpression::ASTForIn(name, iterable, body) => variant("ASTForIn", JsonValue::Array(vec!(string(name), iterable.to_json(), body.to_json()))),
+            ASTExpression::ASTBinaryOp(left, operator, right) => variant("ASTBinaryOp", JsonValue::Array(vec!(left.to_json(), string(operator), right.to_json()))),
+            ASTExpression::ASTUnaryOp(operator, operand) => variant("ASTUnaryOp", JsonValue::Array(vec!(string(operator), operand.to_json()))),
+            ASTExpression::ASTFieldAccess(target, field) => variant("ASTFieldAccess", JsonValue::Array(vec!(target.to_json(), string(field)))),
+            ASTExpression::ASTIndex(target, index) => variant("ASTIndex", JsonValue::Array(vec!(target.to_json(), index.to_json()))),
+            ASTExpression::ASTReturn(value) => variant("ASTReturn", value.to_json()),
+            ASTExpression::ASTBreak => string("ASTBreak"),
+            ASTExpression::ASTContinue => string("ASTContinue"),
+            ASTExpression::ASTMatch(value, arms) => variant("ASTMatch", JsonValue::Array(vec!(
+                value.to_json(),
+                JsonValue::Array(arms.iter().map(|(pattern, body)| JsonValue::Array(vec!(pattern.to_json(), body.to_json()))).collect()),
+            ))),
+            ASTExpression::ASTImport { path, alias } => variant("ASTImport", fields(vec!(
+                ("path", JsonValue::Array(path.iter().map(|segment| string(segment)).collect())),
+                ("alias", optional_string(alias)),
+            ))),
+            ASTExpression::ASTModule(name, body) => variant("ASTModule", JsonValue::Array(vec!(string(name), nodes(body)))),
+            ASTExpression::ASTTraitDef(name, body) => variant("ASTTraitDef", JsonValue::Array(vec!(string(name), nodes(body)))),
+            ASTExpression::ASTEnumDef(name, variants) => variant("ASTEnumDef", JsonValue::Array(vec!(string(name), JsonValue::Array(variants.iter().map(|name| string(name)).collect())))),
+            ASTExpression::ASTStructDef(name, struct_fields) => variant("ASTStructDef", JsonValue::Array(vec!(
+                string(name),
+                JsonValue::Array(struct_fields.iter().map(|(field, field_type)| JsonValue::Array(vec!(string(field), string(field_type)))).collect()),
+            ))),
+            ASTExpression::ASTImpl { type_name, trait_name, body } => variant("ASTImpl", fields(vec!(
+                ("type_name", string(type_name)),
+                ("trait_name", optional_string(trait_name)),
+                ("body", nodes(body)),
+            ))),
+            ASTExpression::ASTNull => string("ASTNull"),
+            ASTExpression::ASTWildcard => string("ASTWildcard"),
+            ASTExpression::ASTUnit => string("ASTUnit"),
+        }
+    }
+}
+
+/// Turns a whole file's AST into pretty printed JSON
+pub fn ast_to_json(ast: &[ASTNode]) -> String {
+    JsonValue::Array(ast.iter().map(|node| node.to_json()).collect()).to_pretty_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_is_an_externally_tagged_variant() {
+        let json = ASTExpression::ASTInteger(42).to_json();
+        assert_eq!(json, JsonValue::Object(vec!((String::from("ASTInteger"), JsonValue::Number(String::from("42"))))));
+        assert_eq!(json.to_pretty_string(), "{\n  \"ASTInteger\": 42\n}");
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(escape_json_string("say \"hi\"\\\n\t\u{1}é"), "\"say \\\"hi\\\"\\\\\\n\\t\\u0001é\"");
+        assert_eq!(ASTExpression::ASTString(String::from("a\"b")).to_json().to_pretty_string(), "{\n  \"ASTString\": \"a\\\"b\"\n}");
+    }
+
+    #[test]
+    fn non_finite_floats_are_null() {
+        for x in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(ASTExpression::ASTFloat(x).to_json(), variant("ASTFloat", JsonValue::Null));
+        }
+        assert_eq!(ASTExpression::ASTFloat(1.5).to_json(), variant("ASTFloat", JsonValue::Number(String::from("1.5"))));
+    }
+}
@@ -1,20 +1,275 @@
 mod parsing;
 mod parsers;
 mod AST;
+mod type_inference;
+mod type_check;
+mod resolve;
+mod eval;
+mod vm;
+mod bytecode_io;
+mod codegen_c;
+mod codegen_js;
+mod repl;
+mod cli;
+mod watch;
+mod visitor;
+mod json;
+mod sexp;
+mod optimize;
+mod diagnostics;
+mod compiler;
 
-use std::fs;
+use std::{env, fs, process};
+use std::path::Path;
+use std::io::IsTerminal;
 use AST::*;
+use parsing::ParseInput;
+use cli::{Backend, CliArgs, ColorChoice};
 
-const TESTING_FILE_PATH: &str = "test.txt";
+// Prints the output, or writes it to the file given with '--output'.
+fn write_output(cli_args: &CliArgs, output: &[u8]) -> Result<(), String> {
+    match &cli_args.output {
+        Some(path) => fs::write(path, output).map_err(|err| format!("{}: {}", path, err)),
+        None => {
+            print!("{}", String::from_utf8_lossy(output));
+            Ok(())
+        },
+    }
+}
+
+fn run_backend(cli_args: &CliArgs, ast_tree: &[ASTNode]) -> Result<(), String> {
+    match cli_args.backend {
+        Some(Backend::C) => {
+            let output = codegen_c::generate(ast_tree).map_err(|err| format!("{} (line: {}, column: {})", err.message, err.line, err.column))?;
+            write_output(cli_args, output.as_bytes())
+        },
+        Some(Backend::Js) => {
+            let output =
+                if cli_args.uncurry {
+                    codegen_js::JsGenerator::with_uncurried_functions().generate(ast_tree)
+                } else {
+                    codegen_js::generate(ast_tree)
+                };
+            write_output(cli_args, output.as_bytes())
+        },
+        Some(Backend::Vm) => {
+            let instructions = vm::compile_ast(ast_tree).map_err(|err| err.message)?;
+            if cli_args.output.is_some() {
+                write_output(cli_args, &bytecode_io::serialize_instructions(&instructions))
+            } else {
+                let value = vm::VM::new().run(&instructions).map_err(|err| err.message)?;
+                write_output(cli_args, format!("{}\n", value).as_bytes())
+            }
+        },
+        // Without a backend, the file is run with the interpreter (unless only the AST was asked for).
+        None if !cli_args.print_ast && !cli_args.json && !cli_args.sexp => {
+            let value = eval::eval_ast(ast_tree, &mut eval::Env::new()).map_err(|err| format!("{} (line: {}, column: {})", err.message, err.line, err.column))?;
+            write_output(cli_args, format!("{}\n", value).as_bytes())
+        },
+        None => Ok(()),
+    }
+}
+
+// Runs a file written with '--output-bytecode' on the VM and prints the value it ends with.
+fn run_bytecode(cli_args: &CliArgs, path: &str) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+    let instructions = bytecode_io::deserialize_instructions(&bytes).map_err(|err| format!("{}: {} (offset: {})", path, err.message, err.offset))?;
+    let value = vm::VM::new().run(&instructions).map_err(|err| err.message)?;
+    write_output(cli_args, format!("{}\n", value).as_bytes())
+}
+
+fn use_color(cli_args: &CliArgs) -> bool {
+    match cli_args.color {
+        ColorChoice::Auto => std::io::stderr().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+// Warnings are shown against the source when there is a single source file to show, and color is on.
+fn print_warning(cli_args: &CliArgs, warning: &resolve::Warning, source: Option<&str>) {
+    match source {
+        Some(source) if use_color(cli_args) => eprintln!("{}", diagnostics::render_warning(warning, source)),
+        _ => eprintln!("{}", warning),
+    }
+}
+
+// Runs the checks asked for with '--check', printing everything they find. Returns false if any of them found an error.
+// Names are resolved first, and types are only checked once every name resolves, since the type checker would report the same undefined names again.
+fn check(cli_args: &CliArgs, ast_tree: &[ASTNode], source: Option<&str>) -> bool {
+    let resolved = resolve::resolve_ast(ast_tree.to_vec());
+    for warning in &resolved.warnings {
+        print_warning(cli_args, warning, source);
+    }
+    if !resolved.errors.is_empty() {
+        for error in &resolved.errors {
+            eprintln!("{}", error);
+        }
+        return false;
+    }
+    match type_check::type_check_ast(&resolved.ast) {
+        Ok(()) => true,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}:{}: {}", error.line, error.column, error.message);
+            }
+            false
+        },
+    }
+}
+
+// Runs the optimizations asked for with '--optimize'.
+// Constants are folded before dead code is removed, since folding can turn an if into one of its branches.
+fn optimize(cli_args: &CliArgs, ast_tree: &[ASTNode], source: Option<&str>) -> Result<Vec<ASTNode>, String> {
+    let folded = optimize::constant_fold_ast(ast_tree.to_vec()).map_err(|err| format!("{} (line: {}, column: {})", err.message, err.line, err.column))?;
+    let (nodes, warnings) = optimize::dead_code_elim(folded);
+    for warning in &warnings {
+        print_warning(cli_args, warning, source);
+    }
+    Ok(nodes)
+}
+
+// Does everything that was asked for with the parsed program: printing the AST, writing bytecode, and running the backend.
+// The source is only given when the program came from a single file, since that's the only time a location can be shown in the source.
+fn emit(cli_args: &CliArgs, ast_tree: &[ASTNode], source: Option<&str>) {
+    if cli_args.print_ast {
+        println!("{:#?}", ast_tree);
+    }
+
+    if cli_args.json {
+        println!("{}", json::ast_to_json(ast_tree));
+    }
+
+    if cli_args.sexp {
+        println!("{}", sexp::ast_list_to_sexp(ast_tree));
+    }
+
+    if cli_args.check && !check(cli_args, ast_tree, source) {
+        process::exit(1);
+    }
+
+    let optimized;
+    let ast_tree =
+        if cli_args.optimize {
+            match optimize(cli_args, ast_tree, source) {
+                Ok(nodes) => {
+                    optimized = nodes;
+                    &optimized
+                },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                },
+            }
+        } else {
+            ast_tree
+        };
+
+    if let Some(path) = &cli_args.bytecode_output {
+        match vm::compile_ast(ast_tree) {
+            Ok(instructions) => {
+                if let Err(err) = fs::write(path, bytecode_io::serialize_instructions(&instructions)) {
+                    eprintln!("{}", err);
+                }
+            },
+            Err(err) => eprintln!("{}", err.message)
+        }
+    }
+
+    if let Err(err) = run_backend(cli_args, ast_tree) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+// Parses every file given with '--files' and links them into one program.
+fn compile_files(cli_args: &CliArgs) -> Result<Vec<ASTNode>, compiler::CompileError> {
+    let mut compiler = compiler::Compiler::new();
+    for path in &cli_args.files {
+        compiler.add_file(Path::new(path))?;
+    }
+    if cli_args.json_errors {
+        eprintln!("{}", diagnostics::render_json(compiler.errors()));
+    } else {
+        for error in compiler.errors() {
+            eprintln!("{}", error);
+        }
+    }
+    // Every error gets reported first, but a program with parse errors never gets run.
+    if !compiler.errors().is_empty() {
+        process::exit(1);
+    }
+    compiler.link()
+}
 
 fn main() {
-    match fs::read_to_string(TESTING_FILE_PATH) {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli_args = match cli::parse_args(&args) {
+        Ok(cli_args) => cli_args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            process::exit(2);
+        },
+    };
+    if cli_args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if cli_args.repl {
+        repl::run_repl();
+        return;
+    }
+    if let Some(path) = &cli_args.bytecode_input {
+        if let Err(err) = run_bytecode(&cli_args, path) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if !cli_args.files.is_empty() {
+        match compile_files(&cli_args) {
+            Ok(ast_tree) => emit(&cli_args, &ast_tree, None),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            },
+        }
+        return;
+    }
+
+    let input_path = cli_args.input.clone().unwrap_or_default();
+    if cli_args.watch {
+        watch::watch_file(&input_path);
+        return;
+    }
+    match fs::read_to_string(&input_path) {
         Ok(contents) => {
 
-            let ast_tree = parse_ast_text(contents);
+            let source = ParseInput::new(contents.clone());
+            let color = use_color(&cli_args);
+            let ParseFileResult { nodes: ast_tree, errors } = parse_file(contents.clone(), Some(input_path.clone()));
+            if cli_args.json_errors {
+                eprintln!("{}", diagnostics::render_json(&errors));
+            } else {
+                for error in &errors {
+                    if color {
+                        eprintln!("{}", diagnostics::render_colored(error, &contents));
+                    } else {
+                        eprintln!("{}", error.display(&source));
+                    }
+                }
+            }
+            // Recovering from errors is only for reporting all of them at once, so the rest of the file isn't run.
+            if !errors.is_empty() {
+                process::exit(1);
+            }
 
-            println!("{:#?}", ast_tree);
+            emit(&cli_args, &ast_tree, Some(&contents));
         },
-        Err(err) => println!("{}", err)        
+        Err(err) => {
+            eprintln!("{}: {}", input_path, err);
+            process::exit(1);
+        }
     }
 }
@@ -0,0 +1,283 @@
+use crate::AST::*;
+use crate::resolve::Warning;
+
+#[derive(Debug, Clone)]
+pub struct OptimizeError {
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl OptimizeError {
+    pub fn new(message: String, location: ASTLocation) -> Self {
+        OptimizeError {
+            message,
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+// Works out '(operator left right)' when both sides are literals, using the same rules as the interpreter (e.g. integers wrap on overflow).
+// Returns None for anything that can't be worked out ahead of time.
+// Dividing by a literal zero is left alone here, and reported by constant_fold once it knows where the division is.
+fn fold_operator(operator: &str, left: &ASTExpression, right: &ASTExpression) -> Option<ASTExpression> {
+    if !left.is_literal() || !right.is_literal() {
+        return None;
+    }
+    let folded = match (operator, left, right) {
+        ("/", ASTExpression::ASTInteger(_), ASTExpression::ASTInteger(0)) | ("%", ASTExpression::ASTInteger(_), ASTExpression::ASTInteger(0)) => return None,
+        ("+", ASTExpression::ASTInteger(a), ASTExpression::ASTInteger(b)) => ASTExpression::ASTInteger(a.wrapping_add(*b)),
+        ("-", ASTExpression::ASTInteger(a), ASTExpression::ASTInteger(b)) => ASTExpression::ASTInteger(a.wrapping_sub(*b)),
+        ("*", ASTExpression::ASTInteger(a), ASTExpression::ASTInteger(b)) => ASTExpression::ASTInteger(a.wrapping_mul(*b)),
+        ("/", ASTExpression::ASTInteger(a), ASTExpression::ASTInteger(b)) => ASTExpression::ASTInteger(a.wrapping_div(*b)),
+        ("%", ASTExpression::ASTInteger(a), ASTExpression::ASTInteger(b)) => ASTExpression::ASTInteger(a.wrapping_rem(*b)),
+        ("**", ASTExpression::ASTInteger(a), ASTExpression::ASTInteger(b)) => ASTExpression::ASTInteger(a.wrapping_pow(u32::try_from(*b).ok()?)),
+        ("+", ASTExpression::ASTFloat(a), ASTExpression::ASTFloat(b)) => ASTExpression::ASTFloat(a + b),
+        ("-", ASTExpression::ASTFloat(a), ASTExpression::ASTFloat(b)) => ASTExpression::ASTFloat(a - b),
+        ("*", ASTExpression::ASTFloat(a), ASTExpression::ASTFloat(b)) => ASTExpression::ASTFloat(a * b),
+        ("/", ASTExpression::ASTFloat(a), ASTExpression::ASTFloat(b)) => ASTExpression::ASTFloat(a / b),
+        ("%", ASTExpression::ASTFloat(a), ASTExpression::ASTFloat(b)) => ASTExpression::ASTFloat(a % b),
+        ("**", ASTExpression::ASTFloat(a), ASTExpression::ASTFloat(b)) => ASTExpression::ASTFloat(a.powf(*b)),
+        ("+", ASTExpression::ASTString(a), ASTExpression::ASTString(b)) => ASTExpression::ASTString(a.clone() + b),
+        _ => return None,
+    };
+    Some(folded)
+}
+
+// Gets the operator and both sides of '(operator left right)' or 'left operator right'.
+fn operator_call(expression: &ASTExpression) -> Option<(&str, &ASTNode, &ASTNode)> {
+    match expression {
+        ASTExpression::ASTParentheses(nodes) => match nodes.as_slice() {
+            [operator, left, right] => match &operator.expression {
+                ASTExpression::ASTVariableRef(name) => Some((name, left, right)),
+                _ => None,
+            },
+            _ => None,
+        },
+        ASTExpression::ASTBinaryOp(left, operator, right) => Some((operator, left, right)),
+        _ => None,
+    }
+}
+
+fn divides_by_zero(expression: &ASTExpression) -> bool {
+    matches!(operator_call(expression), Some(("/" | "%", ASTNode { expression: ASTExpression::ASTInteger(_), .. }, ASTNode { expression: ASTExpression::ASTInteger(0), .. })))
+}
+
+// Folds a single expression whose children have already been folded.
+fn fold_expression(expression: ASTExpression) -> ASTExpression {
+    if let Some(folded) = operator_call(&expression).and_then(|(operator, left, right)| fold_operator(operator, &left.expression, &right.expression)) {
+        return folded;
+    }
+    match expression {
+        ASTExpression::ASTUnaryOp(operator, operand) => match (operator.as_str(), &operand.expression) {
+            ("-", ASTExpression::ASTInteger(x)) => ASTExpression::ASTInteger(x.wrapping_neg()),
+            ("-", ASTExpression::ASTFloat(x)) => ASTExpression::ASTFloat(-x),
+            ("!", ASTExpression::ASTBool(x)) => ASTExpression::ASTBool(!x),
+            _ => ASTExpression::ASTUnaryOp(operator, operand),
+        },
+        // When the condition is a literal, only the branch that would run is kept.
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => match &condition.expression {
+            ASTExpression::ASTBool(true) => then_branch.expression,
+            ASTExpression::ASTBool(false) => else_branch.expression,
+            _ => ASTExpression::ASTIfElse(condition, then_branch, else_branch),
+        },
+        expression => expression,
+    }
+}
+
+/// Replaces operator calls on literals with their result, so '(* (+ 1 2) 4)' becomes '12'.
+/// This goes through the whole tree, including scopes and function bodies. Dividing a literal by zero is an error.
+pub fn constant_fold(node: ASTNode) -> Result<ASTNode, OptimizeError> {
+    let folded = node.map(&fold_expression);
+    // fold goes through the tree in source order, so the first division by zero in the source is the one reported.
+    let division_by_zero = fold(std::slice::from_ref(&folded), None, |found, node| {
+        found.or(Some(node.location).filter(|_| divides_by_zero(&node.expression)))
+    });
+    match division_by_zero {
+        Some(location) => Err(OptimizeError::new(String::from("Division by zero"), location)),
+        None => Ok(folded),
+    }
+}
+
+/// Constant folds every top-level node in a file
+pub fn constant_fold_ast(ast: Vec<ASTNode>) -> Result<Vec<ASTNode>, OptimizeError> {
+    ast.into_iter().map(constant_fold).collect()
+}
+
+// Whether running this node means the code after it will never run.
+// 'return' and 'break' will be the main cases, and a scope counts if anything directly inside of it always exits.
+fn always_exits(node: &ASTNode) -> bool {
+    match &node.expression {
+        ASTExpression::ASTScope(nodes) => nodes.iter().any(always_exits),
+        ASTExpression::ASTReturn(_) | ASTExpression::ASTBreak | ASTExpression::ASTContinue => true,
+        // An if only always exits when whichever branch can run always exits.
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => match &condition.expression {
+            ASTExpression::ASTBool(true) => always_exits(then_branch),
+            ASTExpression::ASTBool(false) => always_exits(else_branch),
+            _ => always_exits(then_branch) && always_exits(else_branch),
+        },
+        _ => false,
+    }
+}
+
+// Removes everything after the first node that always exits, with a warning for each node that was removed.
+fn eliminate_in_list(nodes: Vec<ASTNode>, warnings: &mut Vec<Warning>) -> Vec<ASTNode> {
+    let mut output = vec!();
+    let mut nodes = nodes.into_iter();
+    for node in nodes.by_ref() {
+        let node = eliminate_in_node(node, warnings);
+        let exits = always_exits(&node);
+        output.push(node);
+        if exits {
+            break;
+        }
+    }
+    for unreachable in nodes {
+        warnings.push(Warning::UnreachableCode { location: unreachable.location });
+    }
+    output
+}
+
+fn eliminate_in_box(node: ASTNode, warnings: &mut Vec<Warning>) -> Box<ASTNode> {
+    Box::new(eliminate_in_node(node, warnings))
+}
+
+fn eliminate_in_node(node: ASTNode, warnings: &mut Vec<Warning>) -> ASTNode {
+    let location = node.location;
+    let expression = match node.expression {
+        ASTExpression::ASTScope(nodes) => ASTExpression::ASTScope(eliminate_in_list(nodes, warnings)),
+        ASTExpression::ASTModule(name, body) => ASTExpression::ASTModule(name, eliminate_in_list(body, warnings)),
+        ASTExpression::ASTTraitDef(name, body) => ASTExpression::ASTTraitDef(name, eliminate_in_list(body, warnings)),
+        ASTExpression::ASTImpl { type_name, trait_name, body } => ASTExpression::ASTImpl { type_name, trait_name, body: eliminate_in_list(body, warnings) },
+        ASTExpression::ASTParentheses(nodes) => ASTExpression::ASTParentheses(nodes.into_iter().map(|node| eliminate_in_node(node, warnings)).collect()),
+        ASTExpression::ASTTuple(nodes) => ASTExpression::ASTTuple(nodes.into_iter().map(|node| eliminate_in_node(node, warnings)).collect()),
+        ASTExpression::ASTList(nodes) => ASTExpression::ASTList(nodes.into_iter().map(|node| eliminate_in_node(node, warnings)).collect()),
+        ASTExpression::ASTInterpolation(nodes) => ASTExpression::ASTInterpolation(nodes.into_iter().map(|node| eliminate_in_node(node, warnings)).collect()),
+        ASTExpression::ASTAssignment(name, value) => ASTExpression::ASTAssignment(name, eliminate_in_box(*value, warnings)),
+        ASTExpression::ASTInitialization(name, value) => ASTExpression::ASTInitialization(name, eliminate_in_box(*value, warnings)),
+        ASTExpression::ASTLetRec(name, value) => ASTExpression::ASTLetRec(name, eliminate_in_box(*value, warnings)),
+        ASTExpression::ASTFunction(parameters, body) => ASTExpression::ASTFunction(parameters, eliminate_in_box(*body, warnings)),
+        // When the condition is a literal (usually after constant folding), only the branch that would run is kept.
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => match condition.expression {
+            ASTExpression::ASTBool(true) => return eliminate_in_node(*then_branch, warnings),
+            ASTExpression::ASTBool(false) => return eliminate_in_node(*else_branch, warnings),
+            _ => ASTExpression::ASTIfElse(eliminate_in_box(*condition, warnings), eliminate_in_box(*then_branch, warnings), eliminate_in_box(*else_branch, warnings)),
+        },
+        ASTExpression::ASTWhile(condition, body) => ASTExpression::ASTWhile(eliminate_in_box(*condition, warnings), eliminate_in_box(*body, warnings)),
+        ASTExpression::ASTForIn(name, iterable, body) => ASTExpression::ASTForIn(name, eliminate_in_box(*iterable, warnings), eliminate_in_box(*body, warnings)),
+        ASTExpression::ASTBinaryOp(left, operator, right) => ASTExpression::ASTBinaryOp(eliminate_in_box(*left, warnings), operator, eliminate_in_box(*right, warnings)),
+        ASTExpression::ASTUnaryOp(operator, operand) => ASTExpression::ASTUnaryOp(operator, eliminate_in_box(*operand, warnings)),
+        ASTExpression::ASTFieldAccess(target, field) => ASTExpression::ASTFieldAccess(eliminate_in_box(*target, warnings), field),
+        ASTExpression::ASTIndex(target, index) => ASTExpression::ASTIndex(eliminate_in_box(*target, warnings), eliminate_in_box(*index, warnings)),
+        ASTExpression::ASTReturn(value) => ASTExpression::ASTReturn(eliminate_in_box(*value, warnings)),
+        ASTExpression::ASTMatch(value, arms) => ASTExpression::ASTMatch(eliminate_in_box(*value, warnings), arms.into_iter().map(|(pattern, body)| (pattern, eliminate_in_node(body, warnings))).collect()),
+        ASTExpression::ASTRange { start, end, inclusive } => ASTExpression::ASTRange { start: eliminate_in_box(*start, warnings), end: eliminate_in_box(*end, warnings), inclusive },
+        expression @ (ASTExpression::ASTVariableRef(_)
+        | ASTExpression::ASTInteger(_)
+        | ASTExpression::ASTFloat(_)
+        | ASTExpression::ASTFloat32(_)
+        | ASTExpression::ASTString(_)
+        | ASTExpression::ASTChar(_)
+        | ASTExpression::ASTBool(_)
+        | ASTExpression::ASTDocComment(_)
+        | ASTExpression::ASTImport { .. }
+        | ASTExpression::ASTStructDef(_, _)
+        | ASTExpression::ASTEnumDef(_, _)
+        | ASTExpression::ASTNull
+        | ASTExpression::ASTWildcard
+        | ASTExpression::ASTBreak
+        | ASTExpression::ASTContinue
+        | ASTExpression::ASTUnit) => expression,
+    };
+    ASTNode { expression, location }
+}
+
+/// Removes code that can never run, like anything after a 'return' in the same scope.
+/// Each removed node gets an UnreachableCode warning. This is best run after constant_fold, since folding can make more code unreachable.
+pub fn dead_code_elim(ast: Vec<ASTNode>) -> (Vec<ASTNode>, Vec<Warning>) {
+    let mut warnings = vec!();
+    let nodes = eliminate_in_list(ast, &mut warnings);
+    (nodes, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<ASTNode> {
+        let ParseFileResult { nodes, errors } = parse_file(text.to_string(), None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        nodes
+    }
+
+    fn fold(text: &str) -> Vec<ASTExpression> {
+        constant_fold_ast(parse(text)).unwrap().into_iter().map(|node| node.expression).collect()
+    }
+
+    #[test]
+    fn adds_integer_literals() {
+        assert_eq!(fold("(+ 1 2)"), vec!(ASTExpression::ASTInteger(3)));
+    }
+
+    #[test]
+    fn folds_nested_calls() {
+        assert_eq!(fold("(* (+ 1 2) 4)"), vec!(ASTExpression::ASTInteger(12)));
+    }
+
+    #[test]
+    fn folds_inside_function_bodies() {
+        assert_eq!(fold("(x) { (+ 1 2) }"), fold("(x) { 3 }"));
+    }
+
+    #[test]
+    fn variable_refs_are_left_alone() {
+        let text = "(+ x 2)";
+        assert_eq!(fold(text), parse(text).into_iter().map(|node| node.expression).collect::<Vec<ASTExpression>>());
+    }
+
+    #[test]
+    fn dividing_by_a_literal_zero_is_an_error() {
+        let err = constant_fold_ast(parse("(/ 1 0)")).unwrap_err();
+        assert_eq!((err.message.as_str(), err.line, err.column), ("Division by zero", 1, 1));
+    }
+
+    #[test]
+    fn division_by_zero_error_points_at_the_division() {
+        let err = constant_fold_ast(parse("let x = 1\n(+ (/ 4 2) (/ 2 0) (% 1 0))")).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 12));
+    }
+
+    fn eliminate(text: &str) -> (Vec<ASTExpression>, Vec<Warning>) {
+        let (nodes, warnings) = dead_code_elim(parse(text));
+        (nodes.into_iter().map(|node| node.expression).collect(), warnings)
+    }
+
+    #[test]
+    fn code_after_return_is_removed() {
+        let (nodes, warnings) = eliminate("{ let x = 0 return x let y = 1 }");
+        let expected: Vec<ASTExpression> = parse("{ let x = 0 return x }").into_iter().map(|node| node.expression).collect();
+        assert_eq!(nodes, expected);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].to_string(), "1:22: warning: Unreachable code");
+    }
+
+    #[test]
+    fn each_unreachable_node_gets_a_warning() {
+        let (_, warnings) = eliminate("{ break 1 2 }");
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn literal_condition_keeps_one_branch() {
+        assert_eq!(eliminate("if true then 1 else 0").0, vec!(ASTExpression::ASTInteger(1)));
+        assert_eq!(eliminate("if false then 1 else 0").0, vec!(ASTExpression::ASTInteger(0)));
+    }
+
+    #[test]
+    fn reachable_code_is_kept() {
+        let (nodes, warnings) = eliminate("{ if x then return 1 else 2 3 }");
+        assert_eq!(nodes.len(), 1);
+        assert!(warnings.is_empty());
+    }
+}
@@ -1,59 +1,365 @@
-use std::{rc::Rc, cell::RefCell, sync::Arc};
+use std::{rc::Weak, cell::RefCell, sync::Arc};
 use crate::parsing::*;
 use crate::AST::*;
 
-pub fn parse_integer(input: &mut ParseInput) -> Result<ASTNode, String> {
+// Words that have a special meaning in the language, so they can't be used as variable names.
+const KEYWORDS: &[&str] = &["let", "letrec", "if", "then", "else", "while", "for", "in", "match", "return", "break", "continue", "true", "false", "null"];
+
+/// Any function that parses a node, whether it is a plain fn like parse_integer or a closure made by one of the parse_*_with_parser functions
+pub type ParserFn<'a> = dyn Fn(&mut ParseInput) -> Result<ASTNode, ParseError> + 'a;
+
+/// What the parse_*_with_parser functions give back
+pub type BoxedParser<'a> = Box<ParserFn<'a>>;
+
+/// The parser that recursive parsers call for the expressions inside of them, which gets shared between all of them
+pub type InteriorParser = Arc<ParserFn<'static>>;
+
+/// The parsers that try_parsers_with_list picks between, which can be added to after it is made
+pub type ParserList = RefCell<Vec<BoxedParser<'static>>>;
+
+pub fn parse_integer(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
     // The pop_next_char_numerical and other similar methods only mutate the ParseInput if the next char matches the predicate.
     // This fact will need to be explicit in the documentation for these methods.
     let first_char = input.pop_next_char_numerical()?;
-    let mut output = first_char.to_string();
-    while let Ok(next_char) = input.pop_next_char_numerical() {
-        output += &next_char.to_string();
+    let output = first_char.to_string() + &chars_to_string(&input.pop_while(|c| c.is_numeric()));
+    // Without this check, something like '0x' would quietly parse as the integer 0 followed by the variable x.
+    if input.get_next_char_alphabetical().is_ok() {
+        return Err(first_char.error(String::from("Invalid integer literal")));
+    }
+    let value = str::parse::<i64>(&output).map_err(|err| first_char.error(format!("Invalid integer literal '{}': {}", output, err)))?;
+    Ok(ASTNode::new(ASTExpression::ASTInteger(value), first_char.line, first_char.column))
+}
+
+// Floats need a '.' with digits on at least one side of it. A trailing 'f' makes the literal 32-bit (e.g. '3.14f').
+pub fn parse_float(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    let mut output = String::new();
+    if let Ok(minus_char) = input.pop_char('-') {
+        output.push(minus_char.char);
+    }
+    // Either side of the '.' can be left off (e.g. '3.' or '.75'), but not both.
+    let whole_digits = chars_to_string(&input.pop_while(|c| c.is_ascii_digit()));
+    output.push_str(&whole_digits);
+    output.push(input.pop_char('.')?.char);
+    // A second '.' means this is the start of a range like '1..5', not a float.
+    if input.get_next_char().is_some_and(|parsed_char| parsed_char.char == '.') {
+        return Err(input.error(String::from("Expected a float, but found a range")));
+    }
+    let fraction_digits = chars_to_string(&input.pop_while(|c| c.is_ascii_digit()));
+    if whole_digits.is_empty() && fraction_digits.is_empty() {
+        return Err(first_char.error(String::from("Expected digits in float literal")));
     }
-    Ok(ASTNode::new(ASTExpression::ASTInteger(str::parse::<i64>(&output).map_err(|err| err.to_string())?), first_char.line, first_char.column))
+    output.push_str(&fraction_digits);
+    let is_float32 = input.pop_char('f').is_ok();
+    if input.get_next_char_alphabetical().is_ok() {
+        return Err(first_char.error(String::from("Invalid float literal")));
+    }
+    let expression =
+        if is_float32 {
+            ASTExpression::ASTFloat32(str::parse::<f32>(&output).map_err(|err| input.error(err.to_string()))?)
+        } else {
+            ASTExpression::ASTFloat(str::parse::<f64>(&output).map_err(|err| input.error(err.to_string()))?)
+        };
+    Ok(ASTNode::new(expression, first_char.line, first_char.column))
+}
+
+// Parses integers written with a radix prefix like '0x', where the prefix letter can be either case.
+pub fn parse_prefixed_integer(input: &mut ParseInput, prefix_char: char, radix: u32) -> Result<ASTNode, ParseError> {
+    let first_char = input.pop_char('0')?;
+    input.pop_char(prefix_char.to_ascii_lowercase()).or_else(|_| input.pop_char(prefix_char.to_ascii_uppercase()))?;
+    let digits = chars_to_string(&input.pop_while(|c| c.is_digit(radix)));
+    if digits.is_empty() || input.get_next_char().is_some_and(|parsed_char| parsed_char.char.is_alphanumeric()) {
+        return Err(first_char.error(format!("Invalid base {} integer literal", radix)));
+    }
+    let value = i64::from_str_radix(&digits, radix).map_err(|_| first_char.error(String::from("Integer literal does not fit in a 64-bit signed integer")))?;
+    Ok(ASTNode::new(ASTExpression::ASTInteger(value), first_char.line, first_char.column))
+}
+
+pub fn parse_hex_integer(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    parse_prefixed_integer(input, 'x', 16)
+}
+
+pub fn parse_octal_integer(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    parse_prefixed_integer(input, 'o', 8)
+}
+
+pub fn parse_binary_integer(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    parse_prefixed_integer(input, 'b', 2)
 }
 
-pub fn parse_string_literal(input: &mut ParseInput) -> Result<ASTNode, String> {
+pub fn parse_string_literal(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
     // The question mark operator at the end of these parser functions will return early if that expression is a Result::Err case.
     // This allows for extremely concise and convenient error-handling.
     let first_char = input.pop_char('"')?;
-    let output = input.pop_until_char('"');
+    // '"""' always starts a multiline string, so an unclosed one shouldn't be read as an empty string followed by another string.
+    if input.match_word("\"\"") {
+        return Err(first_char.error(String::from("Expected a multiline string")));
+    }
+    let mut output = String::new();
+    loop {
+        let next_char = input.pop_next_char_result().map_err(|_| first_char.error(String::from("Unclosed string literal")))?;
+        match next_char.char {
+            '"' => break,
+            '\\' => output.push(parse_escape_sequence(input)?),
+            // An unescaped brace always starts an interpolation, so a bad one (like an empty '{}') is left as an error from the interpolation parser.
+            '{' => return Err(first_char.error(String::from("Expected a string without interpolation"))),
+            c => output.push(c),
+        }
+    }
+    Ok(ASTNode::new(ASTExpression::ASTString(output), first_char.line, first_char.column))
+}
+
+// Splits a string like "Hello, {name}!" into its text fragments and the expressions inside the braces.
+// Strings without any braces are left for parse_string_literal.
+pub fn parse_interpolation_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.pop_char('"')?;
+        let mut output = vec!();
+        let mut fragment = String::new();
+        let mut fragment_start = input.get_next_char_result()?;
+        loop {
+            let next_char = input.pop_next_char_result().map_err(|_| first_char.error(String::from("Unclosed string literal")))?;
+            match next_char.char {
+                '"' => break,
+                '\\' => fragment.push(parse_escape_sequence(input)?),
+                '{' => {
+                    if !fragment.is_empty() {
+                        output.push(ASTNode::new(ASTExpression::ASTString(fragment), fragment_start.line, fragment_start.column));
+                        fragment = String::new();
+                    }
+                    input.skip_spaces_and_newlines()?;
+                    if input.get_next_char_result()?.char == '}' {
+                        return Err(next_char.error(String::from("Empty interpolation")));
+                    }
+                    output.push(interior_parser(input)?);
+                    input.skip_spaces_and_newlines()?;
+                    input.skip_char('}')?;
+                    fragment_start = input.get_next_char_result()?;
+                },
+                c => fragment.push(c),
+            }
+        }
+        if output.is_empty() {
+            return Err(first_char.error(String::from("Expected interpolated string")));
+        }
+        if !fragment.is_empty() {
+            output.push(ASTNode::new(ASTExpression::ASTString(fragment), fragment_start.line, fragment_start.column));
+        }
+        Ok(ASTNode::new(ASTExpression::ASTInterpolation(output), first_char.line, first_char.column))
+    })
+}
+
+// Raw strings never treat a backslash as the start of an escape sequence, so r"\n" is a backslash followed by an n.
+pub fn parse_raw_string(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.pop_char('r')?;
     input.skip_char('"')?;
+    let output = input.pop_until_char('"');
+    input.skip_char('"').map_err(|_| first_char.error(String::from("Unclosed raw string literal")))?;
+    Ok(ASTNode::new(ASTExpression::ASTString(output), first_char.line, first_char.column))
+}
+
+// Triple-quoted strings can span multiple lines and hold single or double quotes, but not three quotes in a row unless one is escaped.
+pub fn parse_multiline_string(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_string("\"\"\"")?;
+    let mut output = String::new();
+    while !input.match_word("\"\"\"") {
+        let next_char = input.pop_next_char_result().map_err(|_| first_char.error(String::from("Unclosed multiline string literal")))?;
+        match next_char.char {
+            '\\' => output.push(parse_escape_sequence(input)?),
+            c => output.push(c),
+        }
+    }
+    input.skip_string("\"\"\"")?;
     Ok(ASTNode::new(ASTExpression::ASTString(output), first_char.line, first_char.column))
 }
 
-pub fn parse_name(input: &mut ParseInput) -> Result<String, String> {
+pub fn parse_char_literal(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.pop_char('\'')?;
+    let content_char = input.pop_next_char_result()?;
+    let output = match content_char.char {
+        '\\' => parse_escape_sequence(input)?,
+        '\'' => return Err(first_char.error(String::from("Empty character literal"))),
+        c => c,
+    };
+    input.skip_char('\'').map_err(|_| first_char.error(String::from("Character literal must contain exactly one character")))?;
+    Ok(ASTNode::new(ASTExpression::ASTChar(output), first_char.line, first_char.column))
+}
+
+// Parses the part of an escape sequence after the backslash.
+// Unicode escapes are either exactly four hex digits like '\u00e9', or up to six inside braces like '\u{1F600}',
+// so '\u1F600' is the character U+1F60 followed by a '0'.
+pub fn parse_escape_sequence(input: &mut ParseInput) -> Result<char, ParseError> {
+    let escape_char = input.pop_next_char_result()?;
+    match escape_char.char {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '\\' => Ok('\\'),
+        '"' => Ok('"'),
+        '\'' => Ok('\''),
+        'u' => {
+            let braced = input.skip_char('{').is_ok();
+            let max_digits = if braced { 6 } else { 4 };
+            let mut digits = String::new();
+            while digits.len() < max_digits {
+                match input.pop_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_ascii_hexdigit() { Ok(parsed_char) } else { Err(String::new()) }) {
+                    Ok(digit) => digits.push(digit.char),
+                    Err(_) => break,
+                }
+            }
+            let well_formed = if braced { !digits.is_empty() && input.skip_char('}').is_ok() } else { digits.len() == 4 };
+            let escape_text = if braced { format!("\\u{{{}}}", digits) } else { format!("\\u{}", digits) };
+            u32::from_str_radix(&digits, 16).ok()
+                .filter(|_| well_formed)
+                .and_then(char::from_u32)
+                .ok_or(escape_char.error(format!("Invalid unicode escape '{}'", escape_text)))
+        },
+        c => Err(escape_char.error(format!("Unknown escape sequence '\\{}'", c))),
+    }
+}
+
+pub fn parse_name(input: &mut ParseInput) -> Result<String, ParseError> {
+    let save_point = input.create_save_point();
     let accepted_nonpreceding_symbols = vec!('_', '-');
-    let first_char = input.pop_next_char_alphabetical()?;
-    let mut output = first_char.to_string();
-    while let Ok(next_char) = input.pop_next_char_alphabetical_or_in_group(&accepted_nonpreceding_symbols) {
-        output += &next_char.to_string();
+    // A leading underscore is allowed so that unused variables can be marked like '_unused'.
+    let first_char = input.pop_next_char_alphabetical_or_in_group(&vec!('_'))?;
+    // Digits are allowed after the first character, so names like 'x2' and 'null1' are read whole instead of stopping at the digit.
+    let rest = input.pop_while(|c| c.is_alphanumeric() || accepted_nonpreceding_symbols.contains(&c));
+    let output = first_char.to_string() + &chars_to_string(&rest);
+    if KEYWORDS.contains(&output.as_str()) {
+        input.load_save_point(save_point);
+        return Err(first_char.error(format!("'{}' is a keyword and can't be used as a name", output)));
     }
     Ok(output)
 }
 
+// Parses 'import a::b::c' with an optional 'as alias' on the end.
+// A '*' is allowed as the final path segment for wildcard imports.
+pub fn parse_import(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_keyword("import")?;
+    input.skip_spaces_and_newlines()?;
+    let mut path = vec!();
+    loop {
+        if let Ok(star_char) = input.pop_char('*') {
+            path.push(star_char.to_string());
+            break;
+        }
+        path.push(parse_name(input)?);
+        if input.skip_string("::").is_err() {
+            break;
+        }
+    }
+    let save_point = input.create_save_point();
+    input.skip_spaces_and_newlines()?;
+    let alias =
+        if input.skip_keyword("as").is_ok() {
+            input.skip_spaces_and_newlines()?;
+            Some(parse_name(input)?)
+        } else {
+            input.load_save_point(save_point);
+            None
+        };
+    Ok(ASTNode::new(ASTExpression::ASTImport { path, alias }, first_char.line, first_char.column))
+}
+
+// 'true' and 'false' are keywords, so words like 'trueness' are left for parse_variable_ref.
+pub fn parse_bool(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    let value = if input.skip_keyword("true").is_ok() {
+        true
+    } else {
+        input.skip_keyword("false")?;
+        false
+    };
+    Ok(ASTNode::new(ASTExpression::ASTBool(value), first_char.line, first_char.column))
+}
+
+pub fn parse_null(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_keyword("null")?;
+    Ok(ASTNode::new(ASTExpression::ASTNull, first_char.line, first_char.column))
+}
+
+pub fn parse_break(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_keyword("break")?;
+    Ok(ASTNode::new(ASTExpression::ASTBreak, first_char.line, first_char.column))
+}
+
+pub fn parse_continue(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_keyword("continue")?;
+    Ok(ASTNode::new(ASTExpression::ASTContinue, first_char.line, first_char.column))
+}
+
+// Consecutive '///' lines are merged into a single doc comment, with one line of text per comment line.
+pub fn parse_doc_comment(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    if !input.match_doc_comment() {
+        return Err(first_char.error(String::from("Expected doc comment")));
+    }
+    let mut lines = vec!();
+    while input.match_doc_comment() {
+        input.skip_string("///")?;
+        let _ = input.skip_char(' ');
+        lines.push(input.pop_until_chars(vec!('\r', '\n')));
+        // Only a doc comment on the very next line continues this one, so a blank line starts a new doc comment.
+        let save_point = input.create_save_point();
+        let _ = input.skip_char('\r');
+        let _ = input.skip_char('\n');
+        input.skip_any_of_chars(vec!(' ', '\t'));
+        if !input.match_doc_comment() {
+            input.load_save_point(save_point);
+        }
+    }
+    Ok(ASTNode::new(ASTExpression::ASTDocComment(lines.join("\n")), first_char.line, first_char.column))
+}
+
 // Since this pattern is so permissive, it is important to put it after other, stricter patterns.
-// It could be good to make a list of language keywords (e.g. 'let') and forbid them from being parsed as variable names.
-pub fn parse_variable_ref(input: &mut ParseInput) -> Result<ASTNode, String> {
-    let accepted_nonpreceding_symbols = vec!('_', '-');
-    let first_char = input.pop_next_char_alphabetical()?;
+// Keywords are rejected by parse_name, so something like 'let' on its own is an error instead of a variable.
+pub fn parse_variable_ref(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    let output = parse_name(input)?;
+    // An 'r' right before a quote is always a raw string, so an unclosed one isn't read as the variable 'r' followed by a string.
+    if output == "r" && input.get_next_char().is_some_and(|parsed_char| parsed_char.char == '"') {
+        return Err(first_char.error(String::from("Expected a raw string")));
+    }
+    Ok(ASTNode::new(ASTExpression::ASTVariableRef(output), first_char.line, first_char.column))
+}
+
+// Operators like '+' and '<=' are parsed as variable references, so they can be called like functions, e.g. '(+ 1 2)'.
+pub fn parse_operator_ref(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let operator_chars = vec!('+', '-', '*', '/', '%', '<', '>', '=', '!');
+    // '//' and '/*' start comments, so they can't be the start of an operator or part of one.
+    let starts_comment = |input: &ParseInput| input.match_word("//") || input.match_word("/*");
+    if starts_comment(input) {
+        let next_char = input.get_next_char_result()?;
+        return Err(next_char.error(String::from("Expected operator, but found a comment")));
+    }
+    let first_char = input.pop_next_char_predicate(&|parsed_char: ParsedChar| if operator_chars.contains(&parsed_char.char) { Ok(parsed_char) } else { Err(format!("Expected operator, but found {}", parsed_char.char)) })?;
     let mut output = first_char.to_string();
-    while let Ok(next_char) = input.pop_next_char_alphabetical_or_in_group(&accepted_nonpreceding_symbols) {
-        output += &next_char.to_string();
+    while let Some(parsed_char) = input.get_next_char() {
+        if !operator_chars.contains(&parsed_char.char) || starts_comment(input) {
+            break;
+        }
+        output.push(parsed_char.char);
+        input.skip_next_char();
     }
     Ok(ASTNode::new(ASTExpression::ASTVariableRef(output), first_char.line, first_char.column))
 }
 
 // Instead of parsing input directly, this function takes in an interior_parser, and then builds a new parser that will continually run that interior_parser within a braced scope.
-pub fn parse_scope_with_parser(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<ASTNode, String>>) -> Box<dyn Fn(&mut ParseInput) -> Result<ASTNode, String>> {
+pub fn parse_scope_with_parser(interior_parser: InteriorParser) -> BoxedParser<'static> {
     Box::new(move | input: &mut ParseInput | {
         let mut output = vec!();
         let start_char = input.pop_char('{')?;
         loop {
-            input.skip_spaces_and_newlines();
+            input.skip_spaces_and_newlines()?;
             match interior_parser(input) {
                 Ok(x) => {
-                    output.push(Box::new(x));
+                    output.push(x);
                 },
                 Err(e) => {
                     if let Ok(()) = input.skip_char('}') {
@@ -67,15 +373,15 @@ pub fn parse_scope_with_parser(interior_parser: Arc<dyn Fn(&mut ParseInput) -> R
     })
 }
 
-pub fn parse_parentheses_with_parser(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<ASTNode, String>>) -> Box<dyn Fn(&mut ParseInput) -> Result<ASTNode, String>> {
+pub fn parse_parentheses_with_parser(interior_parser: InteriorParser) -> BoxedParser<'static> {
     Box::new(move | input: &mut ParseInput | {
         let mut output = vec!();
         let first_char = input.pop_char('(')?;
         loop {
-            input.skip_spaces_and_newlines();
+            input.skip_spaces_and_newlines()?;
             match interior_parser(input) {
                 Ok(x) => {
-                    output.push(Box::new(x));
+                    output.push(x);
                 },
                 Err(e) => {
                     if let Ok(()) = input.skip_char(')') {
@@ -89,38 +395,91 @@ pub fn parse_parentheses_with_parser(interior_parser: Arc<dyn Fn(&mut ParseInput
     })
 }
 
-pub fn parse_parentheses_with_parsers<'a>(parsers: Box<Vec<&'a (dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String> + 'a)>>) -> Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String> + 'a> {
+// Tuples are comma separated, which is how they are told apart from space separated parentheses.
+// A single element tuple needs a trailing comma, like '(1,)', and '()' is the empty tuple.
+pub fn parse_tuple_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let mut output = vec!();
+        let first_char = input.pop_char('(')?;
+        let mut found_comma = false;
+        input.skip_spaces_and_newlines()?;
+        while input.skip_char(')').is_err() {
+            output.push(interior_parser(input)?);
+            input.skip_spaces_and_newlines()?;
+            if input.skip_char(',').is_ok() {
+                found_comma = true;
+                input.skip_spaces_and_newlines()?;
+            } else if input.skip_char(')').is_ok() {
+                break;
+            } else {
+                return Err(input.error(String::from("Expected ',' or ')' in tuple")));
+            }
+        }
+        if !output.is_empty() && !found_comma {
+            return Err(input.error(String::from("Expected ',' in tuple")));
+        }
+        Ok(ASTNode::new(ASTExpression::ASTTuple(output), first_char.line, first_char.column))
+    })
+}
+
+// Parses a list like '[1, 2, 3]'. A trailing comma is allowed, so '[1, 2,]' is the same list.
+pub fn parse_list_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let mut output = vec!();
+        let first_char = input.pop_char('[')?;
+        input.skip_spaces_and_newlines()?;
+        while input.skip_char(']').is_err() {
+            output.push(interior_parser(input)?);
+            input.skip_spaces_and_newlines()?;
+            if input.skip_char(',').is_ok() {
+                input.skip_spaces_and_newlines()?;
+            } else if input.skip_char(']').is_ok() {
+                break;
+            } else {
+                return Err(input.error(String::from("Expected ',' or ']' in list")));
+            }
+        }
+        Ok(ASTNode::new(ASTExpression::ASTList(output), first_char.line, first_char.column))
+    })
+}
+
+pub fn parse_parentheses_with_parsers<'a>(parsers: Vec<&'a ParserFn<'a>>) -> BoxedParser<'a> {
     Box::new(move | input: &mut ParseInput | {
         let mut output = vec!();
         let first_char = input.pop_char('(')?;
         while input.skip_char(')').is_err() {
-            output.push(Box::new(try_parsers(input, parsers.to_vec())?));
+            output.push(try_parsers(input, parsers.to_vec())?);
         }
         Ok(ASTNode::new(ASTExpression::ASTParentheses(output), first_char.line, first_char.column))
     })
 }
 
-pub fn parse_assignment_with_parser<'a>(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<ASTNode, String>>) -> Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String> + 'a> {
+pub fn parse_assignment_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
     Box::new(move | input: &mut ParseInput | {
         let mut initialization = false;
         let mut first_char =
-            if input.match_word("let") {
+            if input.match_keyword("let") {
                 initialization = true;
                 input.get_next_char()
             } else {
                 None
             };
-        println!("{}", initialization);
-        let _ = input.skip_string("let");
-        input.skip_spaces_and_newlines();
+        if initialization {
+            input.skip_keyword("let")?;
+        }
+        input.skip_spaces_and_newlines()?;
         if !initialization {
             let new_first_char = input.get_next_char_result()?;
             first_char = Some(new_first_char)
         }
         let variable_name = parse_name(input)?;
-        input.skip_spaces_and_newlines();
+        input.skip_spaces_and_newlines()?;
         input.skip_char('=')?;
-        input.skip_spaces_and_newlines();
+        // 'x == 1' is a comparison, so it gets left for the binary operator parser.
+        if input.match_word("=") {
+            return Err(input.error(String::from("Expected an assignment, but found '=='")));
+        }
+        input.skip_spaces_and_newlines()?;
         let variable_value = interior_parser(input)?;
 
         if initialization {
@@ -131,47 +490,486 @@ pub fn parse_assignment_with_parser<'a>(interior_parser: Arc<dyn Fn(&mut ParseIn
     })
 }
 
+// Parses 'if <condition> then <expression> else <expression>'. The else branch is required, since the whole thing is an expression.
+pub fn parse_if_else_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("if")?;
+        input.skip_spaces_and_newlines()?;
+        let condition = interior_parser(input)?;
+        input.skip_spaces_and_newlines()?;
+        input.skip_keyword("then").map_err(|_| input.error(String::from("Expected 'then' in if expression")))?;
+        input.skip_spaces_and_newlines()?;
+        let then_branch = interior_parser(input)?;
+        input.skip_spaces_and_newlines()?;
+        input.skip_keyword("else").map_err(|_| input.error(String::from("Expected 'else' in if expression")))?;
+        input.skip_spaces_and_newlines()?;
+        let else_branch = interior_parser(input)?;
+        Ok(ASTNode::new(ASTExpression::ASTIfElse(Box::new(condition), Box::new(then_branch), Box::new(else_branch)), first_char.line, first_char.column))
+    })
+}
+
+// Parses 'return <expression>'. The expression has to start on the same line, and a bare 'return' gives back null.
+pub fn parse_return_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("return")?;
+        let save_point = input.create_save_point();
+        input.skip_spaces();
+        let value = match interior_parser(input) {
+            Ok(value) => value,
+            Err(_) => {
+                input.load_save_point(save_point);
+                ASTNode::new(ASTExpression::ASTNull, first_char.line, first_char.column)
+            },
+        };
+        Ok(ASTNode::new(ASTExpression::ASTReturn(Box::new(value)), first_char.line, first_char.column))
+    })
+}
+
+// Parses a pattern on the left side of a match arm. Integer and boolean literals only match themselves,
+// '_' matches anything, and a name matches anything while binding the value to that name.
+pub fn parse_pattern(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    if let Ok(literal) = parse_bool(input).or_else(|_| parse_integer(input)) {
+        return Ok(literal);
+    }
+    let first_char = input.get_next_char_result()?;
+    let name = parse_name(input)?;
+    let expression = if name == "_" { ASTExpression::ASTWildcard } else { ASTExpression::ASTVariableRef(name) };
+    Ok(ASTNode::new(expression, first_char.line, first_char.column))
+}
+
+// Parses 'match <expression> { <pattern> => <expression>, ... }'. There has to be at least one arm, and a trailing comma is allowed.
+pub fn parse_match_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    let parentheses_parser = parse_parentheses_with_parser(interior_parser.clone());
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("match")?;
+        input.skip_spaces_and_newlines()?;
+        let save_point = input.create_save_point();
+        let value = interior_parser(input)?;
+        // Like with while loops, a value in parentheses followed by the arms looks just like a function, e.g. 'match (x) { 1 => 2 }'.
+        // The arms got parsed as the function's body, so the value gets parsed again as only the parentheses.
+        let value = match value.expression {
+            ASTExpression::ASTFunction(_, _) => {
+                input.load_save_point(save_point);
+                parentheses_parser(input)?
+            },
+            _ => value,
+        };
+        input.skip_spaces_and_newlines()?;
+        input.skip_char('{')?;
+        let mut arms = vec!();
+        loop {
+            input.skip_spaces_and_newlines()?;
+            if !arms.is_empty() && input.skip_char('}').is_ok() {
+                break;
+            }
+            let pattern = parse_pattern(input)?;
+            input.skip_spaces_and_newlines()?;
+            input.skip_string("=>").map_err(|_| input.error(String::from("Expected '=>' in match arm")))?;
+            input.skip_spaces_and_newlines()?;
+            arms.push((pattern, interior_parser(input)?));
+            input.skip_spaces_and_newlines()?;
+            if input.skip_char(',').is_err() {
+                input.skip_char('}').map_err(|_| input.error(String::from("Expected ',' or '}' in match")))?;
+                break;
+            }
+        }
+        Ok(ASTNode::new(ASTExpression::ASTMatch(Box::new(value), arms), first_char.line, first_char.column))
+    })
+}
+
+// Parses 'while <condition> { ... }'. The body has to be a scope, so there's no question of where the condition ends.
+pub fn parse_while_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    let scope_parser = parse_scope_with_parser(interior_parser.clone());
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("while")?;
+        input.skip_spaces_and_newlines()?;
+        let condition = interior_parser(input)?;
+        // A condition in parentheses followed by the body looks just like a function, e.g. 'while (< i 10) { ... }'.
+        // A function can never be a condition, so it gets split back into the condition and the body.
+        let (condition, body) = match condition.expression {
+            ASTExpression::ASTFunction(parameters, body) => (*parameters, *body),
+            _ => {
+                input.skip_spaces_and_newlines()?;
+                let body = scope_parser(input).map_err(|err| match input.get_next_char() {
+                    Some(parsed_char) if parsed_char.char != '{' => input.error(String::from("Expected '{' after the condition of the while loop")),
+                    _ => err,
+                })?;
+                (condition, body)
+            },
+        };
+        Ok(ASTNode::new(ASTExpression::ASTWhile(Box::new(condition), Box::new(body)), first_char.line, first_char.column))
+    })
+}
+
+// Parses 'for <name> in <expression> { ... }'. Like with while loops, a list in parentheses followed by the body gets split back apart.
+pub fn parse_for_in_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    let scope_parser = parse_scope_with_parser(interior_parser.clone());
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("for")?;
+        input.skip_spaces_and_newlines()?;
+        let variable_name = parse_name(input)?;
+        input.skip_spaces_and_newlines()?;
+        input.skip_keyword("in").map_err(|_| input.error(String::from("Expected 'in' in for loop")))?;
+        input.skip_spaces_and_newlines()?;
+        let iterable = interior_parser(input)?;
+        let (iterable, body) = match iterable.expression {
+            ASTExpression::ASTFunction(parameters, body) => (*parameters, *body),
+            _ => {
+                input.skip_spaces_and_newlines()?;
+                let body = scope_parser(input).map_err(|err| match input.get_next_char() {
+                    Some(parsed_char) if parsed_char.char != '{' => input.error(String::from("Expected '{' after the list of the for loop")),
+                    _ => err,
+                })?;
+                (iterable, body)
+            },
+        };
+        Ok(ASTNode::new(ASTExpression::ASTForIn(variable_name, Box::new(iterable), Box::new(body)), first_char.line, first_char.column))
+    })
+}
+
+// Parses 'letrec name = (...) { ... }'. Unlike a regular 'let', the function body is allowed to refer to its own name.
+pub fn parse_let_rec_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("letrec")?;
+        input.skip_spaces_and_newlines()?;
+        let variable_name = parse_name(input)?;
+        input.skip_spaces_and_newlines()?;
+        input.skip_char('=')?;
+        input.skip_spaces_and_newlines()?;
+        let variable_value = interior_parser(input)?;
+        if !matches!(variable_value.expression, ASTExpression::ASTFunction(_, _)) {
+            return Err(input.error(format!("Expected a function for 'letrec {}'", variable_name)));
+        }
+        Ok(ASTNode::new(ASTExpression::ASTLetRec(variable_name, Box::new(variable_value)), first_char.line, first_char.column))
+    })
+}
+
+// Parses a '{ ... }' block of items for declarations like modules and traits.
+// This is the same loop as parse_scope_with_parser, but it hands back the items instead of an ASTScope node.
+pub fn parse_braced_items(input: &mut ParseInput, interior_parser: &dyn Fn(&mut ParseInput) -> Result<ASTNode, ParseError>) -> Result<Vec<ASTNode>, ParseError> {
+    input.skip_char('{')?;
+    let mut output = vec!();
+    loop {
+        input.skip_spaces_and_newlines()?;
+        match interior_parser(input) {
+            Ok(x) => {
+                output.push(x);
+            },
+            Err(e) => {
+                if let Ok(()) = input.skip_char('}') {
+                    return Ok(output);
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+// Parses 'struct Name { field: Type, other: Type }'. A trailing comma is allowed, and so are structs without any fields.
+pub fn parse_struct_def(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_keyword("struct")?;
+    input.skip_spaces_and_newlines()?;
+    let struct_name = parse_name(input)?;
+    input.skip_spaces_and_newlines()?;
+    input.skip_char('{')?;
+    input.skip_spaces_and_newlines()?;
+    let mut fields = vec!();
+    while input.skip_char('}').is_err() {
+        let field_name = parse_name(input)?;
+        input.skip_spaces();
+        input.skip_char(':')?;
+        input.skip_spaces();
+        let field_type = parse_name(input)?;
+        fields.push((field_name, field_type));
+        input.skip_spaces_and_newlines()?;
+        if input.skip_char(',').is_ok() {
+            input.skip_spaces_and_newlines()?;
+        } else if input.skip_char('}').is_ok() {
+            break;
+        } else {
+            return Err(input.error(String::from("Expected ',' or '}' in struct")));
+        }
+    }
+    Ok(ASTNode::new(ASTExpression::ASTStructDef(struct_name, fields), first_char.line, first_char.column))
+}
+
+// Parses 'enum Name { First, Second }'. Variants are plain names, so a ':' after one means this is a struct instead.
+pub fn parse_enum_def(input: &mut ParseInput) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    input.skip_keyword("enum")?;
+    input.skip_spaces_and_newlines()?;
+    let enum_name = parse_name(input)?;
+    input.skip_spaces_and_newlines()?;
+    input.skip_char('{')?;
+    input.skip_spaces_and_newlines()?;
+    let mut variants = vec!();
+    while input.skip_char('}').is_err() {
+        variants.push(parse_name(input)?);
+        input.skip_spaces_and_newlines()?;
+        if input.skip_char(',').is_ok() {
+            input.skip_spaces_and_newlines()?;
+        } else if input.skip_char('}').is_ok() {
+            break;
+        } else {
+            return Err(input.error(String::from("Expected ',' or '}' in enum")));
+        }
+    }
+    Ok(ASTNode::new(ASTExpression::ASTEnumDef(enum_name, variants), first_char.line, first_char.column))
+}
+
+// Parses 'module Name { ... }', where the braces can hold anything that is allowed at the top level of a file.
+pub fn parse_module_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("module")?;
+        input.skip_spaces_and_newlines()?;
+        let module_name = parse_name(input)?;
+        input.skip_spaces_and_newlines()?;
+        let body = parse_braced_items(input, &*interior_parser)?;
+        Ok(ASTNode::new(ASTExpression::ASTModule(module_name, body), first_char.line, first_char.column))
+    })
+}
+
+// Parses 'trait Name { ... }'. Methods with a default implementation are written as regular functions, like '(self) { ... }'.
+// Abstract methods leave off the body, so they are just a parameter list like '(self other)'.
+pub fn parse_trait_def_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("trait")?;
+        input.skip_spaces_and_newlines()?;
+        let trait_name = parse_name(input)?;
+        input.skip_spaces_and_newlines()?;
+        let body = parse_braced_items(input, &*interior_parser)?;
+        Ok(ASTNode::new(ASTExpression::ASTTraitDef(trait_name, body), first_char.line, first_char.column))
+    })
+}
+
+// Parses 'impl Type { ... }' as well as 'impl Trait for Type { ... }'.
+// The body holds the definitions for the type, like 'let show = (self) { ... }'.
+pub fn parse_impl_block_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let first_char = input.get_next_char_result()?;
+        input.skip_keyword("impl")?;
+        input.skip_spaces_and_newlines()?;
+        let first_name = parse_name(input)?;
+        input.skip_spaces_and_newlines()?;
+        let (type_name, trait_name) =
+            if input.skip_keyword("for").is_ok() {
+                input.skip_spaces_and_newlines()?;
+                let type_name = parse_name(input)?;
+                input.skip_spaces_and_newlines()?;
+                (type_name, Some(first_name))
+            } else {
+                (first_name, None)
+            };
+        let body = parse_braced_items(input, &*interior_parser)?;
+        Ok(ASTNode::new(ASTExpression::ASTImpl { type_name, trait_name, body }, first_char.line, first_char.column))
+    })
+}
+
+// A field access is a '.' right after an expression, followed by a name.
+// A digit after the '.' means it isn't a field access, and neither does a second '.', so '.5' and '..' are left alone.
+fn match_field_access(input: &ParseInput) -> bool {
+    input.peek_n(2).is_some_and(|chars| chars[0].char == '.' && (chars[1].char.is_alphabetic() || chars[1].char == '_'))
+}
+
+// Parses the '.name' after the target expression.
+fn parse_field_access(input: &mut ParseInput, target: ASTNode) -> Result<ASTNode, ParseError> {
+    input.skip_char('.')?;
+    let field = parse_name(input)?;
+    let (line, column) = (target.location.line, target.location.column);
+    Ok(ASTNode::new(ASTExpression::ASTFieldAccess(Box::new(target), field), line, column))
+}
+
+// Parses the '[index]' after the target expression. The brackets are parsed as a list by the operand_parser, which has to hold exactly one item.
+fn parse_index(input: &mut ParseInput, target: ASTNode, operand_parser: &dyn Fn(&mut ParseInput) -> Result<ASTNode, ParseError>) -> Result<ASTNode, ParseError> {
+    let bracket_char = input.get_next_char_result()?;
+    let index = match operand_parser(input)?.expression {
+        ASTExpression::ASTList(mut nodes) if nodes.len() == 1 => nodes.remove(0),
+        _ => return Err(bracket_char.error(String::from("Expected a single index"))),
+    };
+    let (line, column) = (target.location.line, target.location.column);
+    Ok(ASTNode::new(ASTExpression::ASTIndex(Box::new(target), Box::new(index)), line, column))
+}
+
+// Parses an expression with the operand_parser, and then keeps applying postfix operators like 'a.b' and 'a[0]' to it for as long as there are any.
+// Like field access, an index has to come right after the expression, since '[' after a space starts a new list.
+// Expressions without anything after them are returned unchanged.
+pub fn parse_postfix_with_parser<'a>(operand_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | {
+        let mut output = operand_parser(input)?;
+        loop {
+            output = if match_field_access(input) {
+                parse_field_access(input, output)?
+            } else if input.match_word("[") {
+                parse_index(input, output, &*operand_parser)?
+            } else {
+                return Ok(output);
+            };
+        }
+    })
+}
+
+// Infix operators, with longer operators first so that '<=' isn't read as '<' followed by '='.
+const INFIX_OPERATORS: &[&str] = &["..=", "..", "**", "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "%", "<", ">"];
+
+/// Gets how tightly an infix operator binds to its left and right sides, or None if it isn't an infix operator.
+/// A higher number binds tighter. Every operator is left associative except for '**'.
+/// Ranges bind the loosest, so 'a + 1..b - 1' does the arithmetic first.
+pub fn infix_binding_power(operator: &str) -> Option<(u8, u8)> {
+    match operator {
+        ".." | "..=" => Some((5, 6)),
+        "&&" | "||" => Some((10, 11)),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => Some((20, 21)),
+        "+" | "-" => Some((30, 31)),
+        "*" | "/" | "%" => Some((40, 41)),
+        "**" => Some((50, 49)),
+        _ => None,
+    }
+}
+
+// Looks for an infix operator at the cursor without moving it. '//' and '/*' start comments, so they don't count as division.
+fn match_infix_operator(input: &ParseInput) -> Option<&'static str> {
+    if input.match_word("//") || input.match_word("/*") {
+        return None;
+    }
+    input.match_any_of_strings(INFIX_OPERATORS)
+}
+
+// A prefix operator only counts when it is right up against its operand, so '-x' is a negation while '- x' and '(- x 1)' still refer to the operator itself.
+fn starts_prefix_operand(parsed_char: ParsedChar) -> bool {
+    parsed_char.char.is_alphanumeric() || ['_', '(', '[', '"', '\'', '.', '-', '!'].contains(&parsed_char.char)
+}
+
+/// Parses a prefix '-' (negation) or '!' (logical not), which binds tighter than any infix operator.
+/// Anything that doesn't start with one of them is handed to the operand_parser.
+pub fn parse_unary_op(input: &mut ParseInput, operand_parser: &dyn Fn(&mut ParseInput) -> Result<ASTNode, ParseError>) -> Result<ASTNode, ParseError> {
+    let first_char = input.get_next_char_result()?;
+    let is_prefix_operator = ['-', '!'].contains(&first_char.char)
+        && input.peek_n(2).is_some_and(|chars| starts_prefix_operand(chars[1]));
+    if !is_prefix_operator {
+        return operand_parser(input);
+    }
+    // Float literals can start with a '-' themselves, so those are kept as literals instead of becoming a negation.
+    input.push_save_point();
+    if let Ok(literal @ ASTNode { expression: ASTExpression::ASTFloat(_) | ASTExpression::ASTFloat32(_), .. }) = operand_parser(input) {
+        input.pop_save_point();
+        return Ok(literal);
+    }
+    input.restore_save_point();
+    input.skip_next_char();
+    let operand = parse_unary_op(input, operand_parser)?;
+    Ok(ASTNode::new(ASTExpression::ASTUnaryOp(first_char.to_string(), Box::new(operand)), first_char.line, first_char.column))
+}
+
+// The Pratt parsing loop. Operators that bind looser than min_binding_power are left for a caller further up.
+fn parse_binary_op(input: &mut ParseInput, operand_parser: &dyn Fn(&mut ParseInput) -> Result<ASTNode, ParseError>, min_binding_power: u8) -> Result<ASTNode, ParseError> {
+    let mut left = parse_unary_op(input, operand_parser)?;
+    // Every way through the loop either restores or pops the save point, so the save stack is left as it was found.
+    loop {
+        input.push_save_point();
+        let spaced_before = input.get_next_char().is_some_and(|parsed_char| parsed_char.char.is_whitespace());
+        // Only spaces are skipped here, since a newline ends the expression.
+        input.skip_spaces();
+        let operator = match match_infix_operator(input) {
+            Some(operator) => operator,
+            None => {
+                input.restore_save_point();
+                break;
+            },
+        };
+        // A '-' with a space before it but not after it is a negation starting the next item, like the '-3' in '(f -3)'.
+        let prefix_after = input.peek_n(operator.chars().count() + 1).is_some_and(|chars| starts_prefix_operand(chars[chars.len() - 1]));
+        if operator == "-" && spaced_before && prefix_after {
+            input.restore_save_point();
+            break;
+        }
+        let (left_binding_power, right_binding_power) = infix_binding_power(operator).unwrap_or((0, 0));
+        if left_binding_power < min_binding_power {
+            input.restore_save_point();
+            break;
+        }
+        input.pop_save_point();
+        input.skip_string(operator)?;
+        input.skip_spaces_and_newlines()?;
+        let right = parse_binary_op(input, operand_parser, right_binding_power)?;
+        let (line, column) = (left.location.line, left.location.column);
+        let expression = match operator {
+            ".." | "..=" => ASTExpression::ASTRange { start: Box::new(left), end: Box::new(right), inclusive: operator == "..=" },
+            _ => ASTExpression::ASTBinaryOp(Box::new(left), operator.to_string(), Box::new(right)),
+        };
+        left = ASTNode::new(expression, line, column);
+    }
+    Ok(left)
+}
+
+// Parses infix expressions like '1 + 2 * 3' with operator precedence, using the operand_parser for everything between the operators.
+// An expression without any operators after it is returned unchanged.
+pub fn parse_binary_op_with_parser<'a>(operand_parser: InteriorParser) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | parse_binary_op(input, &*operand_parser, 0))
+}
+
+// When every parser fails, the error that got the furthest into the input is kept, since it most likely comes from the parser that was meant to match.
+// Ties go to the later parser.
+fn furthest_error(current: Option<ParseError>, new: ParseError) -> Option<ParseError> {
+    match current {
+        Some(current) if (current.line, current.column) > (new.line, new.column) => Some(current),
+        _ => Some(new),
+    }
+}
+
 // A simpler version of try_parsers that does not support adding in more parsers later for recursion purposes
-pub fn try_parsers(input: &mut ParseInput, parsers: Vec<&dyn Fn(&mut ParseInput) -> Result<ASTNode, String>>) -> Result<ASTNode, String> {
+pub fn try_parsers(input: &mut ParseInput, parsers: Vec<&ParserFn>) -> Result<ASTNode, ParseError> {
     let save_point = input.create_save_point();
-    let mut last_err = String::new();
+    let mut best_err = None;
     for parser in parsers {
         match parser(input) {
             Ok(x) => return Ok(x),
             Err(err) => {
-                last_err = err;
+                best_err = furthest_error(best_err, err);
                 input.load_save_point(save_point);
             }
         }
     }
-    Err(last_err.to_string())
+    Err(best_err.unwrap_or_else(|| input.error(String::from("No parsers to try"))))
 }
 
-/// Tries every parser in a list. Returns the first successful parse result, or the last error if all fail. 
-pub fn try_parsers_with_list<'a>(parsers: Rc<RefCell<Vec<Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String>>>>>) -> Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String> + 'a> {
-    Box::new(move | input: &mut ParseInput | -> Result<ASTNode, String> {
+/// Tries every parser in a list. Returns the first successful parse result, or the error that got the furthest if all fail.
+// The list holds parsers that call back into this one, so it only keeps a Weak reference to the list to avoid a reference cycle.
+// Whoever builds the list is responsible for keeping it alive for as long as this parser is used.
+pub fn try_parsers_with_list<'a>(parsers: Weak<ParserList>) -> BoxedParser<'a> {
+    Box::new(move | input: &mut ParseInput | -> Result<ASTNode, ParseError> {
+        let parsers = parsers.upgrade().ok_or_else(|| input.error(String::from("The list of parsers was dropped")))?;
         let save_point = input.create_save_point();
-        let mut last_err = String::new();
+        let mut best_err = None;
         for parser in RefCell::borrow(&parsers).iter() {
-            input.skip_spaces_and_newlines();
+            input.skip_spaces_and_newlines()?;
             match parser(input) {
                 Ok(x) => return Ok(x),
                 Err(err) => {
-                    last_err = err;
+                    best_err = furthest_error(best_err, err);
                     input.load_save_point(save_point);
                 }
             }
         }
-        Err(last_err.to_string())
+        Err(best_err.unwrap_or_else(|| input.error(String::from("No parsers to try"))))
     })
 }
 
-pub fn parse_function_with_parser<'a>(interior_parser: Arc<dyn Fn(&mut ParseInput) -> Result<ASTNode, String>>) -> Box<dyn for<'r> Fn(&'r mut ParseInput) -> Result<ASTNode, String> + 'a> {
+pub fn parse_function_with_parser<'a>(interior_parser: InteriorParser) -> BoxedParser<'a> {
     Box::new(move | input: &mut ParseInput | {
         // Grabbing the first char to use for location data.
         let first_char = input.get_next_char_result()?;
         let parameters = parse_parentheses_with_parser(interior_parser.clone())(input)?;
-        input.skip_spaces_and_newlines();
+        input.skip_spaces_and_newlines()?;
         let body = parse_scope_with_parser(interior_parser.clone())(input)?;
         Ok(
             ASTNode::new(
@@ -185,3 +983,418 @@ pub fn parse_function_with_parser<'a>(interior_parser: Arc<dyn Fn(&mut ParseInpu
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<ASTExpression> {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        result.nodes.into_iter().map(|node| node.expression).collect()
+    }
+
+    #[test]
+    fn keyword_followed_by_a_digit_is_a_name() {
+        assert_eq!(parse("null1"), vec!(ASTExpression::ASTVariableRef(String::from("null1"))));
+        assert_eq!(parse("null"), vec!(ASTExpression::ASTNull));
+    }
+
+    #[test]
+    fn unclosed_block_comment_is_an_error() {
+        let result = parse_file(String::from("1\n/* never closed\n2"), None);
+        assert_eq!(result.nodes.len(), 1);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].message.starts_with("Unclosed block comment"), "{}", result.errors[0].message);
+    }
+
+    #[test]
+    fn operator_stops_before_a_comment() {
+        let expected = ASTExpression::ASTParentheses(vec!(
+            ASTNode::new(ASTExpression::ASTVariableRef(String::from("+")), 1, 2),
+            ASTNode::new(ASTExpression::ASTInteger(1), 1, 11),
+            ASTNode::new(ASTExpression::ASTInteger(2), 1, 13),
+        ));
+        assert_eq!(parse("(+/* c */ 1 2)"), vec!(expected));
+    }
+
+    #[test]
+    fn double_equals_is_a_comparison() {
+        let expected = ASTExpression::ASTBinaryOp(
+            Box::new(ASTNode::new(ASTExpression::ASTVariableRef(String::from("x")), 1, 1)),
+            String::from("=="),
+            Box::new(ASTNode::new(ASTExpression::ASTInteger(1), 1, 6)),
+        );
+        assert_eq!(parse("x == 1"), vec!(expected));
+        assert!(matches!(parse("x = 1").as_slice(), [ASTExpression::ASTAssignment(_, _)]));
+    }
+
+    #[test]
+    fn match_on_a_value_in_parentheses() {
+        let expected = ASTExpression::ASTMatch(
+            Box::new(ASTNode::new(ASTExpression::ASTParentheses(vec!(ASTNode::new(ASTExpression::ASTVariableRef(String::from("x")), 1, 8))), 1, 7)),
+            vec!((ASTNode::new(ASTExpression::ASTInteger(1), 1, 13), ASTNode::new(ASTExpression::ASTInteger(2), 1, 18))),
+        );
+        assert_eq!(parse("match (x) { 1 => 2 }"), vec!(expected));
+    }
+
+    fn parse_string_text(text: &str) -> String {
+        match parse(text).as_slice() {
+            [ASTExpression::ASTString(value)] => value.clone(),
+            other => panic!("expected a single string, got {:?}", other),
+        }
+    }
+
+    fn parse_error_message(text: &str) -> String {
+        let result = parse_file(text.to_string(), None);
+        assert_eq!(result.errors.len(), 1, "{:?}", result.errors);
+        result.errors[0].message.clone()
+    }
+
+    #[test]
+    fn each_escape_sequence_is_processed() {
+        assert_eq!(parse_string_text(r#""\n""#), "\n");
+        assert_eq!(parse_string_text(r#""\t""#), "\t");
+        assert_eq!(parse_string_text(r#""\r""#), "\r");
+        assert_eq!(parse_string_text(r#""\"""#), "\"");
+        assert_eq!(parse_string_text(r#""\'""#), "'");
+        assert_eq!(parse_string_text(r#""\u00e9""#), "\u{e9}");
+        assert_eq!(parse_string_text(r#""\u{1F600}""#), "\u{1F600}");
+    }
+
+    #[test]
+    fn doubled_backslash_is_one_backslash() {
+        assert_eq!(parse_string_text(r#""\\""#), "\\");
+        assert_eq!(parse_string_text(r#""\\n""#), "\\n");
+    }
+
+    #[test]
+    fn short_unicode_escape_takes_exactly_four_digits() {
+        assert_eq!(parse_string_text(r#""\u1F600""#), "\u{1F60}0");
+    }
+
+    #[test]
+    fn string_without_escapes_is_unchanged() {
+        assert_eq!(parse_string_text(r#""hello, world""#), "hello, world");
+    }
+
+    #[test]
+    fn bad_escape_sequences_are_errors() {
+        assert!(parse_error_message(r#""\q""#).starts_with("Unknown escape sequence '\\q'"));
+        assert!(parse_error_message(r#""\u12""#).starts_with("Invalid unicode escape '\\u12'"));
+        assert!(parse_error_message(r#""\u{1F600""#).starts_with("Invalid unicode escape '\\u{1F600}'"));
+        assert!(parse_error_message(r#""\u{110000}""#).starts_with("Invalid unicode escape '\\u{110000}'"));
+    }
+
+    #[test]
+    fn failed_integer_parse_points_at_the_start_of_the_literal() {
+        let mut input = ParseInput::new(String::from("\n   99999999999999999999"));
+        input.skip_spaces_and_newlines().unwrap();
+        let error = parse_integer(&mut input).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 4));
+        assert!(error.message.starts_with("Invalid integer literal '99999999999999999999'"), "{}", error.message);
+
+        let error = parse_integer(&mut ParseInput::new(String::from("12abc"))).unwrap_err();
+        assert_eq!((error.line, error.column, error.message.as_str()), (1, 1, "Invalid integer literal"));
+    }
+
+    #[test]
+    fn operators_leave_the_save_stack_empty() {
+        let operand_parser = |input: &mut ParseInput| try_parsers(input, vec!(&parse_float, &parse_integer));
+        let mut input = ParseInput::new(String::from("1 + 2 * -3 - -1.5 )"));
+        let node = parse_binary_op(&mut input, &operand_parser, 0).unwrap();
+        assert_eq!(node.to_string(), "1 + 2 * -3 - -1.5");
+        assert!(input.save_stack.is_empty());
+        assert_eq!(input.get_next_char().map(|parsed_char| parsed_char.char), Some(' '));
+    }
+
+    fn sexp(text: &str) -> Vec<String> {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        result.nodes.iter().map(crate::sexp::ast_to_sexp).collect()
+    }
+
+    #[test]
+    fn import_paths_and_aliases() {
+        assert_eq!(sexp("import std::io"), vec!("(import (std io))"));
+        assert_eq!(sexp("import std::io::write as w"), vec!("(import (std io write) (as w))"));
+        assert_eq!(sexp("import foo"), vec!("(import (foo))"));
+        assert_eq!(sexp("import a::*"), vec!("(import (a *))"));
+    }
+
+    #[test]
+    fn modules_hold_top_level_declarations() {
+        assert_eq!(sexp("module Foo {}"), vec!("(module Foo)"));
+        assert_eq!(sexp("module Math { let double = (x) { (* x 2) } }"), vec!("(module Math (let double (fn (x) (scope (parentheses (variable-ref *) (variable-ref x) (integer 2))))))"));
+        assert_eq!(sexp("module Outer { module Inner { let x = 1 } }"), vec!("(module Outer (module Inner (let x (integer 1))))"));
+        assert_eq!(sexp("let a = 1\nmodule M { let b = 2 }\nlet c = 3"), vec!("(let a (integer 1))", "(module M (let b (integer 2)))", "(let c (integer 3))"));
+    }
+
+    #[test]
+    fn char_literals_hold_one_character() {
+        assert_eq!(parse("'a'"), vec!(ASTExpression::ASTChar('a')));
+        assert_eq!(parse(r"'\n'"), vec!(ASTExpression::ASTChar('\n')));
+        assert_eq!(parse(r"'\''"), vec!(ASTExpression::ASTChar('\'')));
+        assert!(parse_char_literal(&mut ParseInput::new(String::from("'ab'"))).is_err());
+        assert!(!parse_file(String::from("'ab'"), None).errors.is_empty());
+    }
+
+    fn literal_error(parser: fn(&mut ParseInput) -> Result<ASTNode, ParseError>, text: &str) -> String {
+        parser(&mut ParseInput::new(text.to_string())).unwrap_err().message
+    }
+
+    #[test]
+    fn hex_integer_literals() {
+        assert_eq!(parse("0xFF"), vec!(ASTExpression::ASTInteger(255)));
+        assert_eq!(parse("0x1a"), vec!(ASTExpression::ASTInteger(26)));
+        assert_eq!(parse("0XDEADBEEF"), vec!(ASTExpression::ASTInteger(3735928559)));
+        assert_eq!(literal_error(parse_hex_integer, "0x"), "Invalid base 16 integer literal");
+        assert_eq!(literal_error(parse_hex_integer, "0x1G"), "Invalid base 16 integer literal");
+    }
+
+    #[test]
+    fn octal_integer_literals() {
+        assert_eq!(parse("0o7"), vec!(ASTExpression::ASTInteger(7)));
+        assert_eq!(parse("0o10"), vec!(ASTExpression::ASTInteger(8)));
+        assert_eq!(parse("0O777"), vec!(ASTExpression::ASTInteger(511)));
+        assert_eq!(literal_error(parse_octal_integer, "0o8"), "Invalid base 8 integer literal");
+        assert_eq!(literal_error(parse_octal_integer, "0o78"), "Invalid base 8 integer literal");
+    }
+
+    #[test]
+    fn binary_integer_literals() {
+        assert_eq!(parse("0b1010"), vec!(ASTExpression::ASTInteger(10)));
+        assert_eq!(parse("0b0"), vec!(ASTExpression::ASTInteger(0)));
+        assert_eq!(parse("0B11111111"), vec!(ASTExpression::ASTInteger(255)));
+        assert_eq!(literal_error(parse_binary_integer, &format!("0b{}", "1".repeat(64))), "Integer literal does not fit in a 64-bit signed integer");
+        assert_eq!(literal_error(parse_binary_integer, "0b102"), "Invalid base 2 integer literal");
+    }
+
+    #[test]
+    fn multiline_string_literals() {
+        assert_eq!(parse_string_text(r#""""hello""""#), "hello");
+        assert_eq!(parse_string_text(r#""""say "hi" or "" twice""""#), "say \"hi\" or \"\" twice");
+        assert_eq!(parse_string_text(r#""""""""#), "");
+        assert_eq!(parse_string_text("\"\"\"first\nsecond\n\"\"\""), "first\nsecond\n");
+        assert_eq!(parse_string_text(r#""""tab\tend""""#), "tab\tend");
+    }
+
+    #[test]
+    fn raw_strings_keep_backslashes() {
+        assert_eq!(parse_string_text(r#"r"\n""#), "\\n");
+        assert_eq!(parse_string_text(r#"r"hello\world""#).len(), 11);
+        assert_eq!(parse_string_text(r#"r"""#), "");
+    }
+
+    #[test]
+    fn interpolation_splits_text_and_expressions() {
+        assert_eq!(sexp(r#""Hello, {name}!""#), vec!(r#"(interpolation (string "Hello, ") (variable-ref name) (string "!"))"#));
+        assert_eq!(sexp(r#""a {1 + 2} b""#), vec!(r#"(interpolation (string "a ") (binary-op + (integer 1) (integer 2)) (string " b"))"#));
+        assert_eq!(sexp(r#""{x}{y}""#), vec!("(interpolation (variable-ref x) (variable-ref y))"));
+        assert!(parse_error_message(r#""{}""#).starts_with("Empty interpolation"));
+    }
+
+    #[test]
+    fn f_suffix_makes_a_32_bit_float() {
+        assert_eq!(parse("2.75f"), vec!(ASTExpression::ASTFloat32(2.75)));
+        assert_eq!(parse("2.75"), vec!(ASTExpression::ASTFloat(2.75)));
+        assert_eq!(parse("-1.5f"), vec!(ASTExpression::ASTFloat32(-1.5)));
+    }
+
+    #[test]
+    fn commas_make_a_tuple() {
+        assert_eq!(sexp("(1, 2)"), vec!("(tuple (integer 1) (integer 2))"));
+        assert_eq!(sexp("(1,)"), vec!("(tuple (integer 1))"));
+        assert_eq!(sexp("()"), vec!("(tuple)"));
+        assert_eq!(sexp("(1 2)"), vec!("(parentheses (integer 1) (integer 2))"));
+    }
+
+    #[test]
+    fn null_is_a_whole_word() {
+        assert_eq!(parse("nullable"), vec!(ASTExpression::ASTVariableRef(String::from("nullable"))));
+        let mut input = ParseInput::new(String::from("null "));
+        assert_eq!(parse_null(&mut input).unwrap().expression, ASTExpression::ASTNull);
+        assert_eq!(input.remaining_len(), 1);
+    }
+
+    #[test]
+    fn ranges_bind_looser_than_arithmetic() {
+        assert_eq!(sexp("1..5"), vec!("(range (integer 1) (integer 5))"));
+        assert_eq!(sexp("0..=100"), vec!("(range-inclusive (integer 0) (integer 100))"));
+        assert_eq!(sexp("a..b"), vec!("(range (variable-ref a) (variable-ref b))"));
+        assert_eq!(sexp("1 + 2..5"), vec!("(range (binary-op + (integer 1) (integer 2)) (integer 5))"));
+    }
+
+    #[test]
+    fn line_comments_are_skipped() {
+        assert_eq!(parse("// this is a comment\n1"), vec!(ASTExpression::ASTInteger(1)));
+        assert_eq!(parse("1 // after an expression\n2"), vec!(ASTExpression::ASTInteger(1), ASTExpression::ASTInteger(2)));
+        assert_eq!(parse("1 // at the end of the file"), vec!(ASTExpression::ASTInteger(1)));
+    }
+
+    #[test]
+    fn block_comments_nest_and_can_touch_code() {
+        assert_eq!(parse("/* */ 1"), vec!(ASTExpression::ASTInteger(1)));
+        assert_eq!(parse("/* first\nsecond\nthird */ 1"), vec!(ASTExpression::ASTInteger(1)));
+        assert_eq!(parse("/* outer /* inner */ still outer */ 1"), vec!(ASTExpression::ASTInteger(1)));
+        assert_eq!(parse("/*hi*/42"), vec!(ASTExpression::ASTInteger(42)));
+        assert_eq!(parse("/* a *//* b */3"), vec!(ASTExpression::ASTInteger(3)));
+    }
+
+    #[test]
+    fn consecutive_doc_comments_are_merged() {
+        assert_eq!(sexp("/// hello\n/// world\nlet x = 0"), vec!(r#"(doc "hello\nworld")"#, "(let x (integer 0))"));
+        assert_eq!(sexp("/// one\n\n/// two"), vec!(r#"(doc "one")"#, r#"(doc "two")"#));
+        assert_eq!(sexp("// plain\n1"), vec!("(integer 1)"));
+    }
+
+    #[test]
+    fn trait_bodies_hold_methods() {
+        assert_eq!(sexp("trait Printable { (self) {} }"), vec!("(trait Printable (fn (self) (scope)))"));
+        assert_eq!(sexp("trait Empty {}"), vec!("(trait Empty)"));
+        assert_eq!(sexp("trait Named { let name = (self) { self.name } }"), vec!("(trait Named (let name (fn (self) (scope (field-access (variable-ref self) name)))))"));
+    }
+
+    #[test]
+    fn impl_blocks_with_and_without_a_trait() {
+        assert_eq!(sexp("impl Foo { let bar = (x) { x } }"), vec!("(impl Foo (let bar (fn (x) (scope (variable-ref x)))))"));
+        assert_eq!(sexp(r#"impl Show for Foo { let show = (self) { "Foo" } }"#), vec!(r#"(impl Foo (for-trait Show) (let show (fn (self) (scope (string "Foo")))))"#));
+        assert_eq!(sexp("impl Foo {}"), vec!("(impl Foo)"));
+    }
+
+    #[test]
+    fn bools_are_whole_words() {
+        assert_eq!(parse("true"), vec!(ASTExpression::ASTBool(true)));
+        assert_eq!(parse("false"), vec!(ASTExpression::ASTBool(false)));
+        assert_eq!(parse("trueness"), vec!(ASTExpression::ASTVariableRef(String::from("trueness"))));
+    }
+
+    #[test]
+    fn list_literals() {
+        assert_eq!(sexp("[]"), vec!("(list)"));
+        assert_eq!(sexp("[1]"), vec!("(list (integer 1))"));
+        assert_eq!(sexp("[1, 2, 3,]"), vec!("(list (integer 1) (integer 2) (integer 3))"));
+        assert_eq!(sexp("[[1, 2], [3, 4]]"), vec!("(list (list (integer 1) (integer 2)) (list (integer 3) (integer 4)))"));
+        assert_eq!(sexp(r#"[1, "hello", true]"#), vec!(r#"(list (integer 1) (string "hello") (bool true))"#));
+    }
+
+    #[test]
+    fn tuple_needs_a_comma_unless_it_is_empty() {
+        assert_eq!(parse("()"), vec!(ASTExpression::ASTTuple(vec!())));
+        assert_eq!(sexp("(1,)"), vec!("(tuple (integer 1))"));
+        assert_eq!(sexp("(1)"), vec!("(parentheses (integer 1))"));
+        assert_eq!(sexp("((1, 2), 3)"), vec!("(tuple (tuple (integer 1) (integer 2)) (integer 3))"));
+    }
+
+    #[test]
+    fn if_then_else() {
+        assert_eq!(sexp("if true then 1 else 0"), vec!("(if (bool true) (integer 1) (integer 0))"));
+        assert_eq!(sexp("if (< x 10) then x else 10"), vec!("(if (parentheses (variable-ref <) (variable-ref x) (integer 10)) (variable-ref x) (integer 10))"));
+        assert_eq!(sexp("if a then if b then 1 else 2 else 3"), vec!("(if (variable-ref a) (if (variable-ref b) (integer 1) (integer 2)) (integer 3))"));
+        assert_eq!(sexp("{ if a then 1 else 2 }"), vec!("(scope (if (variable-ref a) (integer 1) (integer 2)))"));
+    }
+
+    #[test]
+    fn while_loops() {
+        assert_eq!(sexp("while true {}"), vec!("(while (bool true) (scope))"));
+        assert_eq!(sexp("while (< i 10) { i = (+ i 1) }"), vec!("(while (parentheses (variable-ref <) (variable-ref i) (integer 10)) (scope (set i (parentheses (variable-ref +) (variable-ref i) (integer 1)))))"));
+        assert_eq!(sexp("while a { { 1 } { 2 } }"), vec!("(while (variable-ref a) (scope (scope (integer 1)) (scope (integer 2))))"));
+        assert!(!parse_file(String::from("while"), None).errors.is_empty());
+    }
+
+    #[test]
+    fn for_loops() {
+        assert_eq!(sexp("for x in [1, 2, 3] { x }"), vec!("(for x (list (integer 1) (integer 2) (integer 3)) (scope (variable-ref x)))"));
+        assert_eq!(sexp("for i in 0..10 {}"), vec!("(for i (range (integer 0) (integer 10)) (scope))"));
+        assert_eq!(sexp("for i in xs { for j in ys { j } }"), vec!("(for i (variable-ref xs) (scope (for j (variable-ref ys) (scope (variable-ref j)))))"));
+        assert_eq!(sexp("foreground"), vec!("(variable-ref foreground)"));
+    }
+
+    #[test]
+    fn binary_operators_follow_precedence() {
+        assert_eq!(sexp("1 + 2 * 3"), vec!("(binary-op + (integer 1) (binary-op * (integer 2) (integer 3)))"));
+        assert_eq!(sexp("1 - 2 - 3"), vec!("(binary-op - (binary-op - (integer 1) (integer 2)) (integer 3))"));
+        assert_eq!(sexp("2 ** 3 ** 2"), vec!("(binary-op ** (integer 2) (binary-op ** (integer 3) (integer 2)))"));
+        assert_eq!(sexp("a < b && c"), vec!("(binary-op && (binary-op < (variable-ref a) (variable-ref b)) (variable-ref c))"));
+    }
+
+    #[test]
+    fn unary_operators_bind_tightest() {
+        assert_eq!(sexp("-x"), vec!("(unary-op - (variable-ref x))"));
+        assert_eq!(sexp("!true"), vec!("(unary-op ! (bool true))"));
+        assert_eq!(sexp("--x"), vec!("(unary-op - (unary-op - (variable-ref x)))"));
+        assert_eq!(sexp("-x * y"), vec!("(binary-op * (unary-op - (variable-ref x)) (variable-ref y))"));
+    }
+
+    #[test]
+    fn field_access_chains() {
+        assert_eq!(sexp("foo.bar"), vec!("(field-access (variable-ref foo) bar)"));
+        assert_eq!(sexp("foo.bar.baz"), vec!("(field-access (field-access (variable-ref foo) bar) baz)"));
+        assert_eq!(sexp("(f x).y"), vec!("(field-access (parentheses (variable-ref f) (variable-ref x)) y)"));
+        assert_eq!(sexp(".5"), vec!("(float 0.5)"));
+    }
+
+    #[test]
+    fn indexing_chains_with_field_access() {
+        assert_eq!(sexp("arr[0]"), vec!("(index (variable-ref arr) (integer 0))"));
+        assert_eq!(sexp("arr[x + 1]"), vec!("(index (variable-ref arr) (binary-op + (variable-ref x) (integer 1)))"));
+        assert_eq!(sexp("matrix[0][1]"), vec!("(index (index (variable-ref matrix) (integer 0)) (integer 1))"));
+        assert_eq!(sexp("obj.field[2]"), vec!("(index (field-access (variable-ref obj) field) (integer 2))"));
+        assert_eq!(sexp("a.b[0].c"), vec!("(field-access (index (field-access (variable-ref a) b) (integer 0)) c)"));
+    }
+
+    #[test]
+    fn struct_definitions() {
+        assert_eq!(sexp("struct Point { x: Int, y: Int }"), vec!("(struct Point (x Int) (y Int))"));
+        assert_eq!(sexp("struct Unit {}"), vec!("(struct Unit)"));
+        assert_eq!(sexp("struct P { x: Int, y: Float, }"), vec!("(struct P (x Int) (y Float))"));
+        assert_eq!(sexp("structy"), vec!("(variable-ref structy)"));
+    }
+
+    #[test]
+    fn enum_definitions() {
+        assert_eq!(sexp("enum Color { Red, Green, Blue }"), vec!("(enum Color Red Green Blue)"));
+        assert_eq!(sexp("enum One { A }"), vec!("(enum One A)"));
+        assert_eq!(sexp("enum Empty {}"), vec!("(enum Empty)"));
+        assert_eq!(sexp("enum T { A, B, }"), vec!("(enum T A B)"));
+        assert_eq!(sexp("enumerate"), vec!("(variable-ref enumerate)"));
+    }
+
+    #[test]
+    fn match_arms_take_literals_wildcards_and_names() {
+        assert_eq!(sexp("match x { 1 => 2, _ => 3 }"), vec!("(match (variable-ref x) (arm (integer 1) (integer 2)) (arm (wildcard) (integer 3)))"));
+        assert_eq!(sexp("match x { true => 1, n => n }"), vec!("(match (variable-ref x) (arm (bool true) (integer 1)) (arm (variable-ref n) (variable-ref n)))"));
+        assert_eq!(sexp("match x { 1 => match y { _ => 2 }, _ => 3 }"), vec!("(match (variable-ref x) (arm (integer 1) (match (variable-ref y) (arm (wildcard) (integer 2)))) (arm (wildcard) (integer 3)))"));
+    }
+
+    #[test]
+    fn import_is_not_the_start_of_a_name() {
+        assert_eq!(parse("import a::b as c"), vec!(ASTExpression::ASTImport { path: vec!(String::from("a"), String::from("b")), alias: Some(String::from("c")) }));
+        assert_eq!(parse("important"), vec!(ASTExpression::ASTVariableRef(String::from("important"))));
+    }
+
+    #[test]
+    fn return_with_and_without_a_value() {
+        assert_eq!(sexp("return 42"), vec!("(return (integer 42))"));
+        assert_eq!(sexp("return"), vec!("(return (null))"));
+        assert_eq!(sexp("{ return }"), vec!("(scope (return (null)))"));
+        assert_eq!(sexp("return if x then 1 else 0"), vec!("(return (if (variable-ref x) (integer 1) (integer 0)))"));
+        assert_eq!(sexp("returns"), vec!("(variable-ref returns)"));
+    }
+
+    #[test]
+    fn break_and_continue_are_whole_words() {
+        assert_eq!(parse("break"), vec!(ASTExpression::ASTBreak));
+        assert_eq!(parse("continue"), vec!(ASTExpression::ASTContinue));
+        assert_eq!(parse("breakpoint"), vec!(ASTExpression::ASTVariableRef(String::from("breakpoint"))));
+        assert_eq!(parse("continue_loop"), vec!(ASTExpression::ASTVariableRef(String::from("continue_loop"))));
+    }
+
+    #[test]
+    fn range_bounds_are_computed_first() {
+        assert!(matches!(parse("1..10").as_slice(), [ASTExpression::ASTRange { inclusive: false, .. }]));
+        assert!(matches!(parse("1..=10").as_slice(), [ASTExpression::ASTRange { inclusive: true, .. }]));
+        assert_eq!(sexp("a + 1..b - 1"), vec!("(range (binary-op + (variable-ref a) (integer 1)) (binary-op - (variable-ref b) (integer 1)))"));
+        assert_eq!(sexp("for i in 0..5 { i }"), vec!("(for i (range (integer 0) (integer 5)) (scope (variable-ref i)))"));
+    }
+}
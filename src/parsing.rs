@@ -1,6 +1,65 @@
+use std::fmt;
+
+/// An error from parsing, along with where in the source it happened
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+    // The file the source came from, when there is one.
+    pub source_name: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(message: String, line: u32, column: u32) -> Self {
+        ParseError {
+            message,
+            line,
+            column,
+            source_name: None,
+        }
+    }
+}
+
+impl ParseError {
+    /// Prints the error followed by the source around where it happened
+    pub fn display(&self, input: &ParseInput) -> String {
+        format!("{}\n{}", self, input.get_context_window(self.line, self.column, 2))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source_name {
+            Some(source_name) => write!(f, "{}:{}:{}: {}", source_name, self.line, self.column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+// unicode-segmentation isn't available to this crate, so this covers the common cases by hand:
+// combining marks, variation selectors, emoji skin tone modifiers, and zero-width joiners.
+fn extends_grapheme(char: char) -> bool {
+    matches!(char,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
 pub struct ParseInput {
     pub position: usize,
     pub chars: Vec<ParsedChar>,
+    // The file the text came from, which gets attached to errors.
+    pub source_name: Option<String>,
+    // Positions saved with push_save_point, so that nested parsers can backtrack without passing ParseSavePoints around.
+    pub save_stack: Vec<usize>,
 }
 
 #[derive(Clone, Copy)]
@@ -13,6 +72,11 @@ pub struct ParsedChar {
     pub column: u32,
 }
 
+/// Joins the characters from methods like pop_while back into a String
+pub fn chars_to_string(chars: &[ParsedChar]) -> String {
+    chars.iter().map(|parsed_char| parsed_char.char).collect()
+}
+
 impl ToString for ParsedChar {
     // Implementing this allows parsing functions to quickly convert ParsedChar to char without worrying about the other fields.
     fn to_string(&self) -> String {
@@ -21,12 +85,77 @@ impl ToString for ParsedChar {
 }
 
 impl ParsedChar {
-    fn display_location(&self) -> String {
+    pub fn display_location(&self) -> String {
         format!("line: {}, column: {}", self.line, self.column)
     }
+
+    /// Creates an error located at this character
+    pub fn error(&self, message: String) -> ParseError {
+        ParseError::new(message, self.line, self.column)
+    }
 }
 
 impl ParseInput {
+    /// Gets the line and column of the character at the cursor, or None at the end of the input
+    pub fn get_current_location(&self) -> Option<(u32, u32)> {
+        self.get_next_char().map(|parsed_char| (parsed_char.line, parsed_char.column))
+    }
+
+    // At the end of the input, the location is just past the last character.
+    fn end_location(&self) -> (u32, u32) {
+        match self.chars.last() {
+            Some(last_char) if last_char.char == '\n' => (last_char.line + 1, 1),
+            Some(last_char) => (last_char.line, last_char.column + 1),
+            None => (1, 1),
+        }
+    }
+
+    pub fn current_line(&self) -> u32 {
+        self.get_current_location().unwrap_or_else(|| self.end_location()).0
+    }
+
+    pub fn current_column(&self) -> u32 {
+        self.get_current_location().unwrap_or_else(|| self.end_location()).1
+    }
+
+    /// Gets the text on a line (starting from 1), without the newline at the end
+    pub fn get_line_text(&self, line: u32) -> String {
+        self.chars.iter()
+            .filter(|parsed_char| parsed_char.line == line && parsed_char.char != '\n' && parsed_char.char != '\r')
+            .map(|parsed_char| parsed_char.char)
+            .collect()
+    }
+
+    /// Shows a line of source with a '^' under the given column, for pointing out where an error is
+    pub fn error_context(&self, line: u32, column: u32) -> String {
+        format!("{}\n{}^", self.get_line_text(line), " ".repeat(column.saturating_sub(1) as usize))
+    }
+
+    /// Shows up to context_lines lines on each side of the given line, rustc style, with a '^' line under the given column.
+    /// The window is cut short at the start and end of the input.
+    pub fn get_context_window(&self, line: u32, column: u32, context_lines: usize) -> String {
+        let last_line = self.chars.last().map(|parsed_char| parsed_char.line).unwrap_or(1).max(line);
+        let first_shown = line.saturating_sub(context_lines as u32).max(1);
+        let last_shown = line.saturating_add(context_lines as u32).min(last_line);
+        let gutter_width = last_shown.to_string().len();
+        let mut output = Vec::new();
+        for shown_line in first_shown..=last_shown {
+            output.push(format!("{:>width$} | {}", shown_line, self.get_line_text(shown_line), width = gutter_width));
+            if shown_line == line {
+                output.push(format!("{} | {}^", " ".repeat(gutter_width), " ".repeat(column.saturating_sub(1) as usize)));
+            }
+        }
+        output.join("\n")
+    }
+
+    /// Creates an error located at the cursor
+    pub fn error(&self, message: String) -> ParseError {
+        ParseError {
+            source_name: self.source_name.clone(),
+            ..ParseError::new(message, self.current_line(), self.current_column())
+        }
+    }
+
     /// Gets an option of the next character without moving the cursor
     pub fn get_next_char(&self) -> Option<ParsedChar> {
         // Cloning the ParsedChar on every get isn't great, but it avoids lots of lifetime headaches.
@@ -42,29 +171,46 @@ impl ParseInput {
         self.position = save_point;
     }
 
-    pub fn get_next_char_result(&self) -> Result<ParsedChar, String> {
+    /// Saves the current position on top of the save stack
+    pub fn push_save_point(&mut self) {
+        self.save_stack.push(self.position);
+    }
+
+    /// Discards the top of the save stack without moving the cursor, for when the parse succeeded
+    pub fn pop_save_point(&mut self) {
+        self.save_stack.pop();
+    }
+
+    /// Moves the cursor back to the top of the save stack and discards it. Does nothing if the stack is empty.
+    pub fn restore_save_point(&mut self) {
+        if let Some(position) = self.save_stack.pop() {
+            self.position = position;
+        }
+    }
+
+    pub fn get_next_char_result(&self) -> Result<ParsedChar, ParseError> {
         match self.get_next_char() {
             Some(parsed_char) => Ok(parsed_char),
-            None => Err(String::from("Expected character, but found end of parser input"))
+            None => Err(self.error(String::from("Expected character, but found end of parser input")))
         }
     }
 
-    pub fn get_next_char_predicate(&self, predicate: &dyn Fn(ParsedChar) -> Result<ParsedChar, String>) -> Result<ParsedChar, String> {
+    pub fn get_next_char_predicate(&self, predicate: &dyn Fn(ParsedChar) -> Result<ParsedChar, String>) -> Result<ParsedChar, ParseError> {
         match self.get_next_char() {
-            Some(parsed_char) => predicate(parsed_char),
-            None => Err(String::from("Expected character, but found end of parser input"))
+            Some(parsed_char) => predicate(parsed_char).map_err(|message| parsed_char.error(message)),
+            None => Err(self.error(String::from("Expected character, but found end of parser input")))
         }
     }
 
-    pub fn get_next_char_alphabetical(&self) -> Result<ParsedChar, String> {
+    pub fn get_next_char_alphabetical(&self) -> Result<ParsedChar, ParseError> {
         self.get_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_alphabetic() { Ok(parsed_char) } else { Err(format!("Expected alphabetical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
     }
 
-    pub fn get_next_char_numerical(&self) -> Result<ParsedChar, String> {
+    pub fn get_next_char_numerical(&self) -> Result<ParsedChar, ParseError> {
         self.get_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_numeric() { Ok(parsed_char) } else { Err(format!("Expected numerical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
     }
 
-    pub fn get_next_char_alphabetical_or_in_group(&self, accepted_chars: &Vec<char>) -> Result<ParsedChar, String> {
+    pub fn get_next_char_alphabetical_or_in_group(&self, accepted_chars: &Vec<char>) -> Result<ParsedChar, ParseError> {
         self.get_next_char_predicate(
             &|parsed_char: ParsedChar| {
                 if parsed_char.char.is_alphabetic() || accepted_chars.contains(&parsed_char.char) {
@@ -88,38 +234,38 @@ impl ParseInput {
         }
     }
 
-    pub fn pop_next_char_result(&mut self) -> Result<ParsedChar, String> {
+    pub fn pop_next_char_result(&mut self) -> Result<ParsedChar, ParseError> {
         match self.get_next_char() {
             Some(parsed_char) => {
                 self.skip_next_char();
                 Ok(parsed_char.clone())
             },
-            None => Err(String::from("Expected character, but found end of parser input"))
+            None => Err(self.error(String::from("Expected character, but found end of parser input")))
         }  
     }
 
-    pub fn pop_next_char_predicate(&mut self, predicate: &dyn Fn(ParsedChar) -> Result<ParsedChar, String>) -> Result<ParsedChar, String> {
+    pub fn pop_next_char_predicate(&mut self, predicate: &dyn Fn(ParsedChar) -> Result<ParsedChar, String>) -> Result<ParsedChar, ParseError> {
         match self.get_next_char() {
             Some(parsed_char) => {
-                let x = predicate(parsed_char);
+                let x = predicate(parsed_char).map_err(|message| parsed_char.error(message));
                 if x.is_ok() {
                     self.skip_next_char();
                 }
                 x
             },
-            None => Err(String::from("Expected character, but found end of parser input"))
+            None => Err(self.error(String::from("Expected character, but found end of parser input")))
         }
     }
 
-    pub fn pop_next_char_alphabetical(&mut self) -> Result<ParsedChar, String> {
+    pub fn pop_next_char_alphabetical(&mut self) -> Result<ParsedChar, ParseError> {
         self.pop_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_alphabetic() { Ok(parsed_char) } else { Err(format!("Expected alphabetical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
     }
 
-    pub fn pop_next_char_numerical(&mut self) -> Result<ParsedChar, String> {
+    pub fn pop_next_char_numerical(&mut self) -> Result<ParsedChar, ParseError> {
         self.pop_next_char_predicate(&|parsed_char: ParsedChar| if parsed_char.char.is_numeric() { Ok(parsed_char) } else { Err(format!("Expected numerical character at {}, but found {}", parsed_char.display_location(), parsed_char.char)) })
     }
 
-    pub fn pop_next_char_alphabetical_or_in_group(&mut self, accepted_chars: &Vec<char>) -> Result<ParsedChar, String> {
+    pub fn pop_next_char_alphabetical_or_in_group(&mut self, accepted_chars: &Vec<char>) -> Result<ParsedChar, ParseError> {
         self.pop_next_char_predicate(
             &|parsed_char: ParsedChar| {
                 if parsed_char.char.is_alphabetic() || accepted_chars.contains(&parsed_char.char) {
@@ -142,15 +288,13 @@ impl ParseInput {
     /// Skips the next x number of characters
     pub fn skip_x_chars(&mut self, x: usize) {
         for _ in 0..x {
-            if let Some(_) = self.chars.get(self.position + 1) {
-                self.skip_next_char();
-            }
+            self.skip_next_char();
         }
     }
 
     /// Uses char indices properly.
     /// Gets the text from the cursor position onwards
-    pub fn get_remaining_text(&self) -> Result<String, String> {
+    pub fn get_remaining_text(&self) -> Result<String, ParseError> {
         match self.chars.get(self.position..) {
             Some(parsed_chars) => {
                 let mut output = String::new();
@@ -160,7 +304,7 @@ impl ParseInput {
                 Ok(output)
             },
             None => {
-                Err("Invalid input access".to_string())
+                Err(self.error("Invalid input access".to_string()))
             }
         }
     }
@@ -180,68 +324,116 @@ impl ParseInput {
         Some(output)
     }
 
+    /// Gets the next n characters without moving the cursor or copying them, or None if there aren't that many left
+    pub fn peek_n(&self, n: usize) -> Option<&[ParsedChar]> {
+        self.chars.get(self.position..self.position + n)
+    }
+
     /// Determines if the next block of characters is equal to the predicate string
     pub fn match_word(&self, predicate: &str) -> bool {
-        // This is way too complicated, but it should work
-        let input_string = 
-            self.get_next_x_chars(predicate.len())
-                .map(|parsed_chars|
-                    parsed_chars.into_iter().map(|c| c.to_string())
-                        .reduce(|a, b| a + &b)
-                )
-                .flatten();
-        input_string == Some(predicate.to_string())
+        match self.peek_n(predicate.chars().count()) {
+            Some(parsed_chars) => parsed_chars.iter().map(|parsed_char| parsed_char.char).eq(predicate.chars()),
+            None => false,
+        }
+    }
+
+    /// Gets the first candidate that matches at the cursor. Candidates are tried in order, so longer ones should come first when they share a start (e.g. 'letrec' before 'let').
+    pub fn match_any_of_strings<'a>(&self, candidates: &[&'a str]) -> Option<&'a str> {
+        candidates.iter().find(|candidate| self.match_word(candidate)).copied()
+    }
+
+    /// Determines if the next block of characters is equal to the keyword and is not just the start of a longer name (e.g. 'import' vs 'important')
+    pub fn match_keyword(&self, keyword: &str) -> bool {
+        if !self.match_word(keyword) {
+            return false;
+        }
+        match self.chars.get(self.position + keyword.chars().count()) {
+            Some(parsed_char) => !(parsed_char.char.is_alphanumeric() || parsed_char.char == '_' || parsed_char.char == '-'),
+            None => true,
+        }
     }
 
     pub fn finished(&self) -> bool {
-        self.get_next_char().is_none()
+        self.is_empty()
+    }
+
+    /// Gets the number of characters after the cursor
+    pub fn remaining_len(&self) -> usize {
+        self.remaining_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining_len() == 0
+    }
+
+    /// Gets the characters after the cursor without copying them
+    pub fn remaining_slice(&self) -> &[ParsedChar] {
+        &self.chars[self.position..]
     }
 
-    pub fn pop_char(&mut self, predicate: char) -> Result<ParsedChar, String> {
+    pub fn pop_char(&mut self, predicate: char) -> Result<ParsedChar, ParseError> {
         match self.get_next_char() {
             Some(parsed_char) => {
                 if parsed_char.char == predicate {
                     self.skip_next_char();
                     Ok(parsed_char)
                 } else {
-                    Err(format!("Expected: '{predicate}' at {}, but found '{}'", parsed_char.display_location(), parsed_char.char))
+                    Err(parsed_char.error(format!("Expected: '{predicate}' at {}, but found '{}'", parsed_char.display_location(), parsed_char.char)))
                 }
             },
-            None => Err(format!("Expected: '{predicate}', but found end of parse text"))
+            None => Err(self.error(format!("Expected: '{predicate}', but found end of parse text")))
         }
     }
 
     /// Skips the cursor past an expected character, and returns an error message if the expected character is not found.
-    pub fn skip_char(&mut self, predicate: char) -> Result<(), String> {
+    pub fn skip_char(&mut self, predicate: char) -> Result<(), ParseError> {
         match self.get_next_char() {
             Some(parsed_char) => {
                 if parsed_char.char == predicate {
                     self.skip_next_char();
                     Ok(())
                 } else {
-                    Err(format!("Expected: '{predicate}' at {}, but found '{}'", parsed_char.display_location(), parsed_char.char))
+                    Err(parsed_char.error(format!("Expected: '{predicate}' at {}, but found '{}'", parsed_char.display_location(), parsed_char.char)))
                 }
             },
-            None => Err(format!("Expected: '{predicate}', but found end of parse text"))
+            None => Err(self.error(format!("Expected: '{predicate}', but found end of parse text")))
         }
     }
 
     /// Skips the cursor past an expected string, and returns an error message if the expected string is not found.
-    pub fn skip_string(&mut self, predicate: &str) -> Result<(), String> {
+    pub fn skip_string(&mut self, predicate: &str) -> Result<(), ParseError> {
         if self.match_word(predicate) {
-            self.skip_x_chars(predicate.len());
+            self.skip_x_chars(predicate.chars().count());
+            Ok(())
+        } else {
+            if let Some(next_char) = self.get_next_char() {
+                Err(next_char.error(format!("Expected keyword '{}' at, {}", predicate, next_char.display_location())))
+            } else {
+                Err(self.error(format!("Expected keyword '{}'", predicate)))
+            }
+        }
+    }
+
+    /// Skips the cursor past an expected keyword, and returns an error message if the keyword is not found or is only the start of a longer name.
+    pub fn skip_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.match_keyword(keyword) {
+            self.skip_x_chars(keyword.chars().count());
             Ok(())
         } else {
             if let Some(next_char) = self.get_next_char() {
-                Err(format!("Expected keyword '{}' at, {}", predicate, next_char.display_location()))
+                Err(next_char.error(format!("Expected keyword '{}' at, {}", keyword, next_char.display_location())))
             } else {
-                Err(format!("Expected keyword '{}'", predicate))
+                Err(self.error(format!("Expected keyword '{}'", keyword)))
             }
         }
     }
 
+    /// Skips past every repeat of skip_char at the cursor, stopping at the first different character
     pub fn skip_any_of_char(&mut self, skip_char: char) {
-        while let Some(parsed_char) = self.get_next_char()  {
+        while let Some(parsed_char) = self.get_next_char() {
+            if parsed_char.char != skip_char {
+                break;
+            }
             self.skip_next_char();
         }
     }
@@ -265,56 +457,358 @@ impl ParseInput {
         self.skip_any_of_char(' ');
     }
 
-    pub fn skip_spaces_and_newlines(&mut self) {
-        self.skip_any_of_chars(vec!(' ', '\n', '\r'));
+    /// Skips whitespace along with any comments, since comments can go anywhere that whitespace can.
+    /// Returns an error for a block comment that is never closed, instead of leaving the '/*' to be parsed as code.
+    pub fn skip_spaces_and_newlines(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_any_of_chars(vec!(' ', '\t', '\n', '\r'));
+            if self.match_word("/*") {
+                self.skip_block_comment()?;
+            } else if self.skip_line_comment().is_err() {
+                return Ok(());
+            }
+        }
     }
 
-    pub fn pop_until_char(&mut self, stop_char: char) -> String {
-        let mut output = "".to_string();
-        while let Some(parsed_char) = self.get_next_char() {
-            if parsed_char.char == stop_char {
-                return output;
-            } else {
-                self.skip_next_char();
-                output += &parsed_char.char.to_string();
+    /// Determines if the cursor is at a '///' doc comment, which is kept as part of the AST instead of being skipped
+    pub fn match_doc_comment(&self) -> bool {
+        self.match_word("///") && !self.match_word("////")
+    }
+
+    /// Skips a '//' comment up to the end of the line. The newline itself is left in place.
+    pub fn skip_line_comment(&mut self) -> Result<(), ParseError> {
+        if self.match_doc_comment() {
+            return Err(self.error(String::from("Expected a regular comment, but found a doc comment")));
+        }
+        self.skip_string("//")?;
+        self.pop_until_char('\n');
+        Ok(())
+    }
+
+    /// Skips a '/* */' comment, keeping track of depth so that nested block comments are closed correctly.
+    /// If the comment is never closed, the cursor is left at the opening '/*'.
+    pub fn skip_block_comment(&mut self) -> Result<(), ParseError> {
+        let save_point = self.create_save_point();
+        let first_char = self.get_next_char_result()?;
+        self.skip_string("/*")?;
+        let mut depth = 1;
+        while depth > 0 {
+            if self.skip_string("/*").is_ok() {
+                depth += 1;
+            } else if self.skip_string("*/").is_ok() {
+                depth -= 1;
+            } else if self.pop_next_char().is_none() {
+                self.load_save_point(save_point);
+                return Err(first_char.error(String::from("Unclosed block comment")));
             }
         }
-        output
+        Ok(())
     }
 
-    pub fn pop_until_chars(&mut self, stop_chars: Vec<char>) -> String {
-        let mut output = "".to_string();
+    /// Pops characters for as long as the predicate holds, leaving the cursor at the first character that doesn't match
+    pub fn pop_while(&mut self, predicate: impl Fn(char) -> bool) -> Vec<ParsedChar> {
+        let mut output = Vec::new();
         while let Some(parsed_char) = self.get_next_char() {
-            if stop_chars.contains(&parsed_char.char) {
-                return output;
-            } else {
-                self.skip_next_char();
-                output += &parsed_char.char.to_string();
+            if !predicate(parsed_char.char) {
+                break;
             }
+            self.skip_next_char();
+            output.push(parsed_char);
         }
         output
     }
 
+    /// Pops characters until the predicate holds, leaving the cursor at the character that matched
+    pub fn pop_until(&mut self, predicate: impl Fn(char) -> bool) -> Vec<ParsedChar> {
+        self.pop_while(|c| !predicate(c))
+    }
+
+    pub fn pop_until_char(&mut self, stop_char: char) -> String {
+        self.pop_until(|c| c == stop_char).iter().map(|parsed_char| parsed_char.char).collect()
+    }
+
+    pub fn pop_until_chars(&mut self, stop_chars: Vec<char>) -> String {
+        self.pop_until(|c| stop_chars.contains(&c)).iter().map(|parsed_char| parsed_char.char).collect()
+    }
+
     pub fn new(text: String) -> Self {
         let mut chars: Vec<ParsedChar> = Vec::new();
         let mut line: u32 = 1;
-        for line_text in text.split('\n') {
-            let mut column: u32 = 1;
-            for char in line_text.chars() {
-                chars.push(
-                    ParsedChar {
-                        char,
-                        column,
-                        line,
-                    }
-                );
+        let mut column: u32 = 1;
+        let mut after_joiner = false;
+        // Newlines are kept in the character list so that parsers can tell where one line ends and the next begins.
+        for char in text.chars() {
+            // Characters that extend the previous grapheme cluster share its column, so 'a\u{0301}b' puts 'b' at column 2.
+            let extends_previous = column > 1 && (after_joiner || extends_grapheme(char));
+            if extends_previous {
+                column -= 1;
+            }
+            chars.push(
+                ParsedChar {
+                    char,
+                    column,
+                    line,
+                }
+            );
+            after_joiner = char == '\u{200D}';
+            if char == '\n' {
+                line += 1;
+                column = 1;
+            } else {
                 column += 1;
             }
-            line += 1;
         }
         ParseInput {
             position: 0,
             chars,
+            source_name: None,
+            save_stack: Vec::new(),
+        }
+    }
+
+    /// Creates a ParseInput for text that came from a file, so that errors can say which file they are in
+    pub fn with_name(text: String, name: impl Into<String>) -> Self {
+        ParseInput {
+            source_name: Some(name.into()),
+            ..ParseInput::new(text)
         }
     }
-}
\ No newline at end of file
+
+    /// Decodes UTF-8 bytes into a ParseInput, without needing a String first
+    #[cfg(test)]
+    pub fn new_from_bytes(bytes: &[u8]) -> Result<Self, std::str::Utf8Error> {
+        let text = std::str::from_utf8(bytes)?;
+        Ok(ParseInput::new(text.to_string()))
+    }
+
+    /// Reads a whole file into a ParseInput. The path is used as the source name in errors.
+    #[cfg(test)]
+    pub fn new_from_file(path: &std::path::Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ParseInput::with_name(text, path.display().to_string()))
+    }
+}
+
+/// Iterating over a ParseInput pops each character in turn, so the cursor moves along with the iterator
+impl Iterator for ParseInput {
+    type Item = ParsedChar;
+
+    fn next(&mut self) -> Option<ParsedChar> {
+        self.pop_next_char()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_line_text_returns_each_line() {
+        let input = ParseInput::new(String::from("let x = 1\n(+ x 2)"));
+        assert_eq!(input.get_line_text(1), "let x = 1");
+        assert_eq!(input.get_line_text(2), "(+ x 2)");
+    }
+
+    #[test]
+    fn error_context_points_at_the_column() {
+        let input = ParseInput::new(String::from("let x = 1\n(+ x 2)"));
+        assert_eq!(input.error_context(2, 4), "(+ x 2)\n   ^");
+    }
+
+    #[test]
+    fn named_input_puts_the_name_in_errors() {
+        let mut input = ParseInput::with_name(String::from("1\n2\nabcd?"), "myfile.txt");
+        input.pop_while(|c| c != '?');
+        let message = input.error(String::from("Unexpected '?'")).to_string();
+        assert!(message.contains("myfile.txt:3:5"), "{}", message);
+    }
+
+    #[test]
+    fn save_points_restore_in_reverse_order() {
+        let mut input = ParseInput::new(String::from("abcd"));
+        input.push_save_point();
+        input.pop_next_char();
+        input.push_save_point();
+        input.pop_next_char();
+        input.restore_save_point();
+        assert_eq!(input.position, 1);
+        input.restore_save_point();
+        assert_eq!(input.position, 0);
+        assert!(input.save_stack.is_empty());
+    }
+
+    #[test]
+    fn popping_a_save_point_keeps_the_cursor() {
+        let mut input = ParseInput::new(String::from("abcd"));
+        input.push_save_point();
+        input.pop_next_char();
+        input.pop_save_point();
+        assert_eq!(input.position, 1);
+        // With nothing saved, restoring does nothing.
+        input.restore_save_point();
+        assert_eq!(input.position, 1);
+    }
+
+    #[test]
+    fn comments_are_skipped_between_tokens() {
+        let mut input = ParseInput::new(String::from("// comment\n1"));
+        input.skip_spaces_and_newlines().unwrap();
+        assert_eq!(input.get_next_char().map(|parsed_char| parsed_char.char), Some('1'));
+    }
+
+    #[test]
+    fn line_comment_stops_at_the_newline() {
+        let mut input = ParseInput::new(String::from("// comment\n1"));
+        input.skip_line_comment().unwrap();
+        assert_eq!(input.get_next_char().map(|parsed_char| parsed_char.char), Some('\n'));
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let mut input = ParseInput::new(String::from("/* outer /* inner */ still outer */1"));
+        input.skip_block_comment().unwrap();
+        assert_eq!(input.get_next_char().map(|parsed_char| parsed_char.char), Some('1'));
+        assert!(ParseInput::new(String::from("/* /* */")).skip_block_comment().is_err());
+    }
+
+    #[test]
+    fn remaining_len_counts_down_as_chars_are_popped() {
+        let mut input = ParseInput::new(String::from("abc"));
+        assert_eq!(input.remaining_len(), 3);
+        assert!(!input.is_empty());
+        input.pop_next_char();
+        input.pop_next_char();
+        assert_eq!(input.remaining_len(), 1);
+        assert_eq!(input.remaining_slice().iter().map(|parsed_char| parsed_char.char).collect::<String>(), "c");
+        input.pop_next_char();
+        assert!(input.is_empty());
+        assert!(input.remaining_slice().is_empty());
+    }
+
+    #[test]
+    fn skip_any_of_char_only_skips_that_char() {
+        let mut input = ParseInput::new(String::from("aaabbb"));
+        input.skip_any_of_char('a');
+        assert_eq!(input.get_remaining_text().unwrap(), "bbb");
+        let mut input = ParseInput::new(String::from("   x"));
+        input.skip_any_of_char(' ');
+        assert_eq!(input.get_remaining_text().unwrap(), "x");
+        input.skip_any_of_char(' ');
+        assert_eq!(input.get_remaining_text().unwrap(), "x");
+    }
+
+    #[test]
+    fn current_location_follows_the_cursor() {
+        let mut input = ParseInput::new(String::from("ab\ncd"));
+        assert_eq!(input.get_current_location(), Some((1, 1)));
+        input.pop_while(|c| c != '\n');
+        assert_eq!(input.get_current_location(), Some((1, 3)));
+        input.pop_next_char();
+        assert_eq!(input.get_current_location(), Some((2, 1)));
+        input.pop_while(|_| true);
+        assert_eq!(input.get_current_location(), None);
+        assert_eq!((input.current_line(), input.current_column()), (2, 3));
+    }
+
+    #[test]
+    fn iterating_pops_every_char_in_order() {
+        let chars: Vec<ParsedChar> = ParseInput::new(String::from("a\nb")).collect();
+        let summary: Vec<(char, u32, u32)> = chars.iter().map(|parsed_char| (parsed_char.char, parsed_char.line, parsed_char.column)).collect();
+        assert_eq!(summary, vec!(('a', 1, 1), ('\n', 1, 2), ('b', 2, 1)));
+
+        let mut input = ParseInput::new(String::from("abc"));
+        assert_eq!(input.by_ref().take(2).count(), 2);
+        assert_eq!(input.get_remaining_text().unwrap(), "c");
+    }
+
+    #[test]
+    fn pop_while_and_pop_until_stop_at_the_boundary() {
+        let mut input = ParseInput::new(String::from("abc123"));
+        assert_eq!(input.pop_while(|c| c.is_alphabetic()).len(), 3);
+        assert_eq!(input.get_remaining_text().unwrap(), "123");
+
+        let mut input = ParseInput::new(String::from("abc123"));
+        assert_eq!(input.pop_until(|c| c.is_numeric()).len(), 3);
+        assert_eq!(input.get_remaining_text().unwrap(), "123");
+        assert!(input.pop_until(|c| c.is_numeric()).is_empty());
+        assert_eq!(input.pop_until_char('x'), "123");
+    }
+
+    #[test]
+    fn peek_n_looks_ahead_without_moving() {
+        let input = ParseInput::new(String::from("hello"));
+        let peeked: String = input.peek_n(3).unwrap().iter().map(|parsed_char| parsed_char.char).collect();
+        assert_eq!(peeked, "hel");
+        assert_eq!(input.remaining_len(), 5);
+        assert!(ParseInput::new(String::from("hi")).peek_n(10).is_none());
+    }
+
+    #[test]
+    fn match_word_checks_the_next_chars() {
+        let input = ParseInput::new(String::from("import"));
+        assert!(input.match_word("imp"));
+        assert!(input.match_word("import"));
+        assert!(!input.match_word("imports"));
+        assert!(!input.match_word("port"));
+    }
+
+    #[test]
+    fn match_any_of_strings_returns_the_first_match() {
+        let candidates = ["letrec", "let"];
+        assert_eq!(ParseInput::new(String::from("letrec x")).match_any_of_strings(&candidates), Some("letrec"));
+        assert_eq!(ParseInput::new(String::from("let x")).match_any_of_strings(&candidates), Some("let"));
+        assert_eq!(ParseInput::new(String::from("foo")).match_any_of_strings(&candidates), None);
+        // Candidates are tried in order, so a shorter one listed first wins.
+        assert_eq!(ParseInput::new(String::from("letrec x")).match_any_of_strings(&["let", "letrec"]), Some("let"));
+    }
+
+    fn columns(text: &str) -> Vec<(char, u32)> {
+        ParseInput::new(text.to_string()).map(|parsed_char| (parsed_char.char, parsed_char.column)).collect()
+    }
+
+    #[test]
+    fn combining_chars_share_a_column() {
+        assert_eq!(columns("a\u{0301}b"), vec!(('a', 1), ('\u{0301}', 1), ('b', 2)));
+        // A zero-width joiner glues the next emoji onto the previous one.
+        assert_eq!(columns("\u{1F468}\u{200D}\u{1F469}x"), vec!(('\u{1F468}', 1), ('\u{200D}', 1), ('\u{1F469}', 1), ('x', 2)));
+        assert_eq!(columns("ab"), vec!(('a', 1), ('b', 2)));
+    }
+
+    #[test]
+    fn input_from_bytes_must_be_utf8() {
+        let input = ParseInput::new_from_bytes("é\nx".as_bytes()).unwrap();
+        assert_eq!(input.get_remaining_text().unwrap(), "é\nx");
+        assert!(ParseInput::new_from_bytes(&[b'a', 0xFF]).is_err());
+    }
+
+    #[test]
+    fn input_from_a_file_is_named_after_the_path() {
+        let path = std::env::temp_dir().join(format!("language_compiler_new_from_file_{}.txt", std::process::id()));
+        std::fs::write(&path, "1\n?").unwrap();
+        let mut input = ParseInput::new_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        input.pop_until_char('?');
+        let message = input.error(String::from("Unexpected '?'")).to_string();
+        assert!(message.contains(&format!("{}:2:1", path.display())), "{}", message);
+        assert!(ParseInput::new_from_file(&path).is_err());
+    }
+
+    #[test]
+    fn context_window_is_cut_short_at_the_ends() {
+        let text: Vec<String> = (1..=10).map(|line| format!("line {}", line)).collect();
+        let input = ParseInput::new(text.join("\n"));
+        assert_eq!(input.get_context_window(5, 3, 2), "3 | line 3\n4 | line 4\n5 | line 5\n  |   ^\n6 | line 6\n7 | line 7");
+        assert_eq!(input.get_context_window(1, 1, 2), "1 | line 1\n  | ^\n2 | line 2\n3 | line 3");
+        assert_eq!(input.get_context_window(10, 6, 1), " 9 | line 9\n10 | line 10\n   |      ^");
+    }
+
+    #[test]
+    fn keywords_must_end_at_a_word_boundary() {
+        assert!(ParseInput::new(String::from("import x")).match_keyword("import"));
+        assert!(ParseInput::new(String::from("import")).match_keyword("import"));
+        assert!(ParseInput::new(String::from("import::x")).match_keyword("import"));
+        assert!(!ParseInput::new(String::from("important")).match_keyword("import"));
+        assert!(!ParseInput::new(String::from("import_x")).match_keyword("import"));
+        assert!(!ParseInput::new(String::from("import-x")).match_keyword("import"));
+    }
+}
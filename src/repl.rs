@@ -0,0 +1,82 @@
+use std::io::{self, BufRead, BufReader, Write};
+use crate::AST::*;
+use crate::parsing::ParseInput;
+use crate::eval::{self, Env};
+
+const PROMPT: &str = "> ";
+
+/// Reads lines from stdin and evaluates each one, keeping variables around between lines.
+/// ':quit' exits, and ':ast <code>' prints the parsed AST instead of evaluating it.
+pub fn run_repl() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(err) = run_repl_with(BufReader::new(stdin.lock()), stdout.lock()) {
+        eprintln!("{}", err);
+    }
+}
+
+// The REPL loop itself, which works on any reader and writer instead of just stdin and stdout.
+pub fn run_repl_with(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut env = Env::new();
+    write!(output, "{}", PROMPT)?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let command = line.trim();
+        if command == ":quit" {
+            return Ok(());
+        }
+        if let Some(code) = command.strip_prefix(":ast") {
+            writeln!(output, "{:#?}", parse_ast_text(code.to_string()))?;
+        } else if !command.is_empty() {
+            // A line with a parse error isn't run at all, even if part of it could be parsed.
+            let ParseFileResult { nodes, errors } = parse_file(line.clone(), None);
+            if errors.is_empty() {
+                match eval::eval_ast(&nodes, &mut env) {
+                    Ok(value) => writeln!(output, "{}", value)?,
+                    Err(err) => writeln!(output, "Error: {} (line: {}, column: {})", err.message, err.line, err.column)?,
+                }
+            } else {
+                // There's only ever one line, so the caret goes right under it without any line numbers.
+                let source = ParseInput::new(line);
+                for error in &errors {
+                    writeln!(output, "{}\n{}", error, source.error_context(error.line, error.column))?;
+                }
+            }
+        }
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+    }
+    // Reaching the end of the input (e.g. Ctrl-D) leaves the prompt hanging, so it gets its own line.
+    writeln!(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn run(input: &str) -> String {
+        let mut output = Vec::new();
+        run_repl_with(Cursor::new(input), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn quit_stops_right_away() {
+        assert_eq!(run(":quit\n1\n"), "> ");
+    }
+
+    #[test]
+    fn variables_last_between_lines() {
+        assert_eq!(run("let x = 1\n(+ x 1)\n"), "> 1\n> 2\n> \n");
+    }
+
+    #[test]
+    fn line_with_a_parse_error_is_not_run() {
+        let output = run("let x = 1\nlet x = (+ x\nx\n");
+        assert!(output.contains("let x = (+ x\n") && output.contains("^"), "{}", output);
+        assert!(!output.contains("()"), "{}", output);
+        assert!(output.ends_with("> 1\n> \n"), "{}", output);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+use crate::AST::*;
+use crate::eval::BUILTIN_OPERATORS;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolveErrorKind {
+    Undefined,
+    UseBeforeDecl,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    pub kind: ResolveErrorKind,
+    pub name: String,
+    pub location: ASTLocation,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ResolveErrorKind::Undefined => write!(f, "{}: Undefined variable '{}'", self.location, self.name),
+            ResolveErrorKind::UseBeforeDecl => write!(f, "{}: '{}' is used before it is declared", self.location, self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Warning {
+    Shadowing { name: String, inner_location: ASTLocation, outer_location: ASTLocation },
+    UnusedVariable { name: String, location: ASTLocation },
+    UnreachableCode { location: ASTLocation },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::Shadowing { name, inner_location, outer_location } => write!(f, "{}: warning: '{}' shadows the declaration at {}", inner_location, name, outer_location),
+            Warning::UnusedVariable { name, location } => write!(f, "{}: warning: '{}' is never used", location, name),
+            Warning::UnreachableCode { location } => write!(f, "{}: warning: Unreachable code", location),
+        }
+    }
+}
+
+impl Warning {
+    /// Where the warning points to in the source
+    pub fn location(&self) -> ASTLocation {
+        match self {
+            Warning::Shadowing { inner_location: location, .. } | Warning::UnusedVariable { location, .. } | Warning::UnreachableCode { location } => *location,
+        }
+    }
+}
+
+/// Everything the resolver found. Warnings don't stop compilation, but errors do.
+#[derive(Debug)]
+pub struct ResolveResult {
+    pub ast: Vec<ASTNode>,
+    pub errors: Vec<ResolveError>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Default)]
+struct ResolverScope {
+    declarations: HashMap<String, ASTLocation>,
+    // Names that are declared somewhere in this scope, but haven't been reached yet.
+    // This is how a use before the declaration is told apart from a name that doesn't exist at all.
+    later_declarations: HashSet<String>,
+    // Variables that haven't been referenced since they were declared.
+    unused: HashMap<String, ASTLocation>,
+}
+
+/// Walks the AST with a stack of scopes and checks that every variable is declared before it is used
+#[derive(Debug, Default)]
+pub struct ScopeResolver {
+    scopes: Vec<ResolverScope>,
+    errors: Vec<ResolveError>,
+    warnings: Vec<Warning>,
+}
+
+impl ScopeResolver {
+    pub fn new() -> Self {
+        ScopeResolver::default()
+    }
+
+    pub fn resolve(mut self, ast: Vec<ASTNode>) -> ResolveResult {
+        let nodes: Vec<&ASTNode> = ast.iter().collect();
+        self.resolve_in_scope(&nodes, &[]);
+        ResolveResult {
+            ast,
+            errors: self.errors,
+            warnings: self.warnings,
+        }
+    }
+
+    fn resolve_in_scope(&mut self, nodes: &[&ASTNode], parameters: &[(String, ASTLocation)]) {
+        let mut scope = ResolverScope::default();
+        for node in nodes {
+            collect_declarations(node, &mut scope.later_declarations);
+        }
+        self.scopes.push(scope);
+        for (name, location) in parameters {
+            self.declare_variable(name, *location);
+        }
+        for node in nodes {
+            self.resolve_node(node);
+        }
+        if let Some(scope) = self.scopes.pop() {
+            self.warn_unused(scope.unused);
+        }
+    }
+
+    fn warn_unused(&mut self, unused: HashMap<String, ASTLocation>) {
+        let mut unused: Vec<(String, ASTLocation)> = unused.into_iter().collect();
+        unused.sort_by_key(|(_, location)| (location.line, location.column));
+        for (name, location) in unused {
+            self.warnings.push(Warning::UnusedVariable { name, location });
+        }
+    }
+
+    fn declare(&mut self, name: &str, location: ASTLocation) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.later_declarations.remove(name);
+            scope.declarations.insert(name.to_string(), location);
+        }
+    }
+
+    // Variables are declared like any other name, but they also get tracked so that a warning can be given if they are never used.
+    // Starting a name with '_' opts out of the warning.
+    fn declare_variable(&mut self, name: &str, location: ASTLocation) {
+        self.declare(name, location);
+        if name.starts_with('_') {
+            return;
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            // A redeclaration in the same scope hides the earlier one for good, so an unused earlier one is reported right away.
+            if let Some(previous_location) = scope.unused.insert(name.to_string(), location) {
+                self.warnings.push(Warning::UnusedVariable { name: name.to_string(), location: previous_location });
+            }
+        }
+    }
+
+    // Shadowing a name from the same scope is just a redeclaration, so only outer scopes are checked.
+    fn check_shadowing(&mut self, name: &str, inner_location: ASTLocation) {
+        if let Some((current_scope, outer_scopes)) = self.scopes.split_last() {
+            if current_scope.declarations.contains_key(name) {
+                return;
+            }
+            if let Some(outer_location) = outer_scopes.iter().rev().find_map(|scope| scope.declarations.get(name)) {
+                self.warnings.push(Warning::Shadowing { name: name.to_string(), inner_location, outer_location: *outer_location });
+            }
+        }
+    }
+
+    fn use_variable(&mut self, name: &str, location: ASTLocation) {
+        if let Some(scope) = self.scopes.iter_mut().rev().find(|scope| scope.declarations.contains_key(name)) {
+            scope.unused.remove(name);
+            return;
+        }
+        if BUILTIN_OPERATORS.contains(&name) {
+            return;
+        }
+        let kind =
+            if self.scopes.iter().any(|scope| scope.later_declarations.contains(name)) {
+                ResolveErrorKind::UseBeforeDecl
+            } else {
+                ResolveErrorKind::Undefined
+            };
+        self.errors.push(ResolveError { kind, name: name.to_string(), location });
+    }
+
+    fn resolve_node(&mut self, node: &ASTNode) {
+        match &node.expression {
+            ASTExpression::ASTVariableRef(name) => self.use_variable(name, node.location),
+            ASTExpression::ASTInitialization(name, value) => {
+                self.resolve_node(value);
+                self.check_shadowing(name, node.location);
+                self.declare_variable(name, node.location);
+            },
+            ASTExpression::ASTAssignment(name, value) => {
+                self.resolve_node(value);
+                self.use_variable(name, node.location);
+            },
+            // The name is declared before the value is resolved, so the function can call itself.
+            ASTExpression::ASTLetRec(name, value) => {
+                self.check_shadowing(name, node.location);
+                self.declare_variable(name, node.location);
+                self.resolve_node(value);
+            },
+            ASTExpression::ASTScope(nodes) => {
+                let nodes: Vec<&ASTNode> = nodes.iter().collect();
+                self.resolve_in_scope(&nodes, &[]);
+            },
+            ASTExpression::ASTFunction(parameters, body) => {
+                self.resolve_in_scope(&[&**body], &function_parameters(parameters));
+            },
+            ASTExpression::ASTParentheses(nodes) | ASTExpression::ASTTuple(nodes) | ASTExpression::ASTList(nodes) | ASTExpression::ASTInterpolation(nodes) => {
+                for child in nodes {
+                    self.resolve_node(child);
+                }
+            },
+            ASTExpression::ASTRange { start, end, .. } => {
+                self.resolve_node(start);
+                self.resolve_node(end);
+            },
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+                self.resolve_node(condition);
+                self.resolve_node(then_branch);
+                self.resolve_node(else_branch);
+            },
+            ASTExpression::ASTWhile(condition, body) => {
+                self.resolve_node(condition);
+                self.resolve_node(body);
+            },
+            ASTExpression::ASTBinaryOp(left, _, right) | ASTExpression::ASTIndex(left, right) => {
+                self.resolve_node(left);
+                self.resolve_node(right);
+            },
+            // Only the target is resolved, since field names aren't variables.
+            ASTExpression::ASTUnaryOp(_, operand) | ASTExpression::ASTFieldAccess(operand, _) | ASTExpression::ASTReturn(operand) => self.resolve_node(operand),
+            // A name used as a pattern is only visible inside of its arm, like a function parameter.
+            ASTExpression::ASTMatch(value, arms) => {
+                self.resolve_node(value);
+                for (pattern, body) in arms {
+                    match &pattern.expression {
+                        ASTExpression::ASTVariableRef(name) => self.resolve_in_scope(&[body], &[(name.clone(), pattern.location)]),
+                        _ => self.resolve_node(body),
+                    }
+                }
+            },
+            // The loop variable is only visible inside of the body, like a function parameter.
+            ASTExpression::ASTForIn(name, iterable, body) => {
+                self.resolve_node(iterable);
+                self.resolve_in_scope(&[&**body], &[(name.clone(), node.location)]);
+            },
+            ASTExpression::ASTModule(name, body) | ASTExpression::ASTTraitDef(name, body) => {
+                self.declare(name, node.location);
+                let body: Vec<&ASTNode> = body.iter().collect();
+                self.resolve_in_scope(&body, &[]);
+            },
+            ASTExpression::ASTImpl { body, .. } => {
+                let body: Vec<&ASTNode> = body.iter().collect();
+                self.resolve_in_scope(&body, &[]);
+            },
+            ASTExpression::ASTImport { path, alias } => {
+                if let Some(name) = imported_name(path, alias) {
+                    self.declare(name, node.location);
+                }
+            },
+            ASTExpression::ASTStructDef(name, _) | ASTExpression::ASTEnumDef(name, _) => self.declare(name, node.location),
+            ASTExpression::ASTInteger(_)
+            | ASTExpression::ASTFloat(_)
+            | ASTExpression::ASTFloat32(_)
+            | ASTExpression::ASTString(_)
+            | ASTExpression::ASTChar(_)
+            | ASTExpression::ASTBool(_)
+            | ASTExpression::ASTDocComment(_)
+            | ASTExpression::ASTNull
+            | ASTExpression::ASTWildcard
+            | ASTExpression::ASTBreak
+            | ASTExpression::ASTContinue
+            | ASTExpression::ASTUnit => {},
+        }
+    }
+}
+
+// The name an import makes available, which is the alias if there is one and the last part of the path otherwise.
+// Wildcard imports don't declare a single name, so they return None.
+fn imported_name<'a>(path: &'a [String], alias: &'a Option<String>) -> Option<&'a str> {
+    match alias {
+        Some(alias) => Some(alias),
+        None => path.last().map(|name| name.as_str()).filter(|name| *name != "*"),
+    }
+}
+
+// Gets the names and locations out of a function's parameter list. Anything that isn't a plain name is skipped.
+fn function_parameters(parameters: &ASTNode) -> Vec<(String, ASTLocation)> {
+    match &parameters.expression {
+        ASTExpression::ASTParentheses(nodes) => nodes.iter()
+            .filter_map(|parameter| match &parameter.expression {
+                ASTExpression::ASTVariableRef(name) => Some((name.clone(), parameter.location)),
+                _ => None,
+            })
+            .collect(),
+        _ => vec!(),
+    }
+}
+
+// Finds every name declared in a scope without going into the scopes nested inside of it.
+fn collect_declarations(node: &ASTNode, declarations: &mut HashSet<String>) {
+    match &node.expression {
+        ASTExpression::ASTInitialization(name, value) | ASTExpression::ASTLetRec(name, value) => {
+            declarations.insert(name.clone());
+            collect_declarations(value, declarations);
+        },
+        ASTExpression::ASTAssignment(_, value) => collect_declarations(value, declarations),
+        ASTExpression::ASTModule(name, _) | ASTExpression::ASTTraitDef(name, _) | ASTExpression::ASTStructDef(name, _) | ASTExpression::ASTEnumDef(name, _) => {
+            declarations.insert(name.clone());
+        },
+        ASTExpression::ASTImport { path, alias } => {
+            if let Some(name) = imported_name(path, alias) {
+                declarations.insert(name.to_string());
+            }
+        },
+        ASTExpression::ASTParentheses(nodes) | ASTExpression::ASTTuple(nodes) | ASTExpression::ASTList(nodes) | ASTExpression::ASTInterpolation(nodes) => {
+            for child in nodes {
+                collect_declarations(child, declarations);
+            }
+        },
+        ASTExpression::ASTRange { start, end, .. } => {
+            collect_declarations(start, declarations);
+            collect_declarations(end, declarations);
+        },
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+            collect_declarations(condition, declarations);
+            collect_declarations(then_branch, declarations);
+            collect_declarations(else_branch, declarations);
+        },
+        ASTExpression::ASTWhile(condition, body) => {
+            collect_declarations(condition, declarations);
+            collect_declarations(body, declarations);
+        },
+        ASTExpression::ASTBinaryOp(left, _, right) | ASTExpression::ASTIndex(left, right) => {
+            collect_declarations(left, declarations);
+            collect_declarations(right, declarations);
+        },
+        ASTExpression::ASTUnaryOp(_, operand) | ASTExpression::ASTFieldAccess(operand, _) | ASTExpression::ASTReturn(operand) => collect_declarations(operand, declarations),
+        ASTExpression::ASTForIn(_, iterable, _) | ASTExpression::ASTMatch(iterable, _) => collect_declarations(iterable, declarations),
+        _ => {},
+    }
+}
+
+/// Resolves a whole file, returning the AST along with any errors and warnings that were found
+pub fn resolve_ast(ast: Vec<ASTNode>) -> ResolveResult {
+    ScopeResolver::new().resolve(ast)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_source(text: &str) -> ResolveResult {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        resolve_ast(result.nodes)
+    }
+
+    #[test]
+    fn declared_variable_resolves() {
+        assert!(resolve_source("let x = 0\nx").errors.is_empty());
+    }
+
+    #[test]
+    fn undeclared_variable_is_undefined() {
+        let errors = resolve_source("y").errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ResolveErrorKind::Undefined);
+        assert_eq!(errors[0].name, "y");
+    }
+
+    #[test]
+    fn use_before_declaration() {
+        let errors = resolve_source("(x let x = 0)").errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ResolveErrorKind::UseBeforeDecl);
+    }
+
+    fn shadowing_count(warnings: &[Warning]) -> usize {
+        warnings.iter().filter(|warning| matches!(warning, Warning::Shadowing { .. })).count()
+    }
+
+    #[test]
+    fn inner_scope_shadows_outer_binding() {
+        let result = resolve_source("let x = 0\n{ let x = 1\n x }\nx");
+        assert_eq!(shadowing_count(&result.warnings), 1);
+        match &result.warnings[0] {
+            Warning::Shadowing { name, inner_location, outer_location } => {
+                assert_eq!(name, "x");
+                assert_eq!((inner_location.line, outer_location.line), (2, 1));
+            },
+            warning => panic!("expected a shadowing warning, found {:?}", warning),
+        }
+    }
+
+    #[test]
+    fn each_shadowing_scope_is_reported() {
+        let result = resolve_source("let x = 0\n{ let x = 1\n x }\n{ let x = 2\n x }\nx");
+        assert_eq!(shadowing_count(&result.warnings), 2);
+    }
+
+    fn unused_names(warnings: &[Warning]) -> Vec<&str> {
+        warnings.iter().filter_map(|warning| match warning {
+            Warning::UnusedVariable { name, .. } => Some(name.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn unused_variable_is_reported() {
+        assert_eq!(unused_names(&resolve_source("let x = 0").warnings), vec!("x"));
+    }
+
+    #[test]
+    fn underscore_silences_unused_warning() {
+        assert!(unused_names(&resolve_source("let _x = 0").warnings).is_empty());
+    }
+
+    #[test]
+    fn unused_parameter_is_reported() {
+        let result = resolve_source("let f = (a b) { a }\nf");
+        assert_eq!(unused_names(&result.warnings), vec!("b"));
+    }
+
+    #[test]
+    fn only_letrec_can_refer_to_itself() {
+        let errors = resolve_source("let f = (n) { (f n) }\nf").errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].kind, errors[0].name.as_str()), (ResolveErrorKind::UseBeforeDecl, "f"));
+        assert_eq!(resolve_source("{ let f = (n) { (f n) }\nf }").errors.len(), 1);
+        assert!(resolve_source("letrec fact = (n) { if n == 0 then 1 else n * (fact (n - 1)) }\nfact").errors.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::AST::*;
+
+fn quote(x: &str) -> String {
+    format!("{:?}", x)
+}
+
+fn list(head: &str, items: Vec<String>) -> String {
+    if items.is_empty() {
+        format!("({})", head)
+    } else {
+        format!("({} {})", head, items.join(" "))
+    }
+}
+
+fn nodes_to_sexp(nodes: &[ASTNode]) -> Vec<String> {
+    nodes.iter().map(ast_to_sexp).collect()
+}
+
+// Parameters are written as a plain list of names like '(x y)'.
+fn parameters_to_sexp(parameters: &ASTNode) -> String {
+    match &parameters.expression {
+        ASTExpression::ASTParentheses(nodes) => {
+            let names: Vec<String> = nodes.iter().map(|node| match &node.expression {
+                ASTExpression::ASTVariableRef(name) => name.clone(),
+                _ => ast_to_sexp(node),
+            }).collect();
+            format!("({})", names.join(" "))
+        },
+        _ => ast_to_sexp(parameters),
+    }
+}
+
+/// Prints a node as an S-expression like '(let x (integer 0))'. Locations are left out, so the output only changes when the tree does.
+pub fn ast_to_sexp(node: &ASTNode) -> String {
+    match &node.expression {
+        ASTExpression::ASTVariableRef(name) => list("variable-ref", vec!(name.clone())),
+        ASTExpression::ASTInteger(x) => list("integer", vec!(x.to_string())),
+        ASTExpression::ASTFloat(x) => list("float", vec!(format!("{:?}", x))),
+        ASTExpression::ASTFloat32(x) => list("float32", vec!(format!("{:?}", x))),
+        ASTExpression::ASTString(x) => list("string", vec!(quote(x))),
+        ASTExpression::ASTInterpolation(nodes) => list("interpolation", nodes_to_sexp(nodes)),
+        ASTExpression::ASTChar(x) => list("char", vec!(quote(&x.to_string()))),
+        ASTExpression::ASTBool(x) => list("bool", vec!(x.to_string())),
+        ASTExpression::ASTAssignment(name, value) => list("set", vec!(name.clone(), ast_to_sexp(value))),
+        ASTExpression::ASTInitialization(name, value) => list("let", vec!(name.clone(), ast_to_sexp(value))),
+        ASTExpression::ASTLetRec(name, value) => list("letrec", vec!(name.clone(), ast_to_sexp(value))),
+        ASTExpression::ASTScope(nodes) => list("scope", nodes_to_sexp(nodes)),
+        ASTExpression::ASTParentheses(nodes) => list("parentheses", nodes_to_sexp(nodes)),
+        ASTExpression::ASTTuple(nodes) => list("tuple", nodes_to_sexp(nodes)),
+        ASTExpression::ASTList(nodes) => list("list", nodes_to_sexp(nodes)),
+        ASTExpression::ASTRange { start, end, inclusive } => list(if *inclusive { "range-inclusive" } else { "range" }, vec!(ast_to_sexp(start), ast_to_sexp(end))),
+        ASTExpression::ASTDocComment(text) => list("doc", vec!(quote(text))),
+        ASTExpression::ASTFunction(parameters, body) => list("fn", vec!(parameters_to_sexp(parameters), ast_to_sexp(body))),
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => list("if", vec!(ast_to_sexp(condition), ast_to_sexp(then_branch), ast_to_sexp(else_branch))),
+        ASTExpression::ASTWhile(condition, body) => list("while", vec!(ast_to_sexp(condition), ast_to_sexp(body))),
+        ASTExpression::ASTForIn(name, iterable, body) => list("for", vec!(name.clone(), ast_to_sexp(iterable), ast_to_sexp(body))),
+        ASTExpression::ASTBinaryOp(left, operator, right) => list("binary-op", vec!(operator.clone(), ast_to_sexp(left), ast_to_sexp(right))),
+        ASTExpression::ASTUnaryOp(operator, operand) => list("unary-op", vec!(operator.clone(), ast_to_sexp(operand))),
+        ASTExpression::ASTFieldAccess(target, field) => list("field-access", vec!(ast_to_sexp(target), field.clone())),
+        ASTExpression::ASTIndex(target, index) => list("index", vec!(ast_to_sexp(target), ast_to_sexp(index))),
+        ASTExpression::ASTReturn(value) => list("return", vec!(ast_to_sexp(value))),
+        ASTExpression::ASTBreak => list("break", vec!()),
+        ASTExpression::ASTContinue => list("continue", vec!()),
+        ASTExpression::ASTMatch(value, arms) => list("match", [vec!(ast_to_sexp(value)), arms.iter().map(|(pattern, body)| list("arm", vec!(ast_to_sexp(pattern), ast_to_sexp(body)))).collect()].concat()),
+        ASTExpression::ASTImport { path, alias } => {
+            let mut items = vec!(format!("({})", path.join(" ")));
+            if let Some(alias) = alias {
+                items.push(list("as", vec!(alias.clone())));
+            }
+            list("import", items)
+        },
+        ASTExpression::ASTModule(name, body) => list("module", [vec!(name.clone()), nodes_to_sexp(body)].concat()),
+        ASTExpression::ASTTraitDef(name, body) => list("trait", [vec!(name.clone()), nodes_to_sexp(body)].concat()),
+        ASTExpression::ASTEnumDef(name, variants) => list("enum", [vec!(name.clone()), variants.clone()].concat()),
+        ASTExpression::ASTStructDef(name, fields) => list("struct", [vec!(name.clone()), fields.iter().map(|(field, field_type)| list(field, vec!(field_type.clone()))).collect()].concat()),
+        ASTExpression::ASTImpl { type_name, trait_name, body } => {
+            let mut items = vec!(type_name.clone());
+            if let Some(trait_name) = trait_name {
+                items.push(list("for-trait", vec!(trait_name.clone())));
+            }
+            list("impl", [items, nodes_to_sexp(body)].concat())
+        },
+        ASTExpression::ASTNull => list("null", vec!()),
+        ASTExpression::ASTWildcard => list("wildcard", vec!()),
+        ASTExpression::ASTUnit => list("unit", vec!()),
+    }
+}
+
+/// Prints every top-level node inside of a single '(program ...)' form, with one node per line
+pub fn ast_list_to_sexp(nodes: &[ASTNode]) -> String {
+    if nodes.is_empty() {
+        return String::from("(program)");
+    }
+    let items: Vec<String> = nodes.iter().map(|node| format!("  {}", ast_to_sexp(node))).collect();
+    format!("(program\n{})", items.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<ASTNode> {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        result.nodes
+    }
+
+    #[test]
+    fn integer_is_tagged_with_its_kind() {
+        assert_eq!(ast_to_sexp(&ASTNode::new(ASTExpression::ASTInteger(42), 1, 1)), "(integer 42)");
+    }
+
+    #[test]
+    fn function_lists_its_parameters_then_its_body() {
+        assert_eq!(ast_to_sexp(&parse("(x) { x }")[0]), "(fn (x) (scope (variable-ref x)))");
+        assert_eq!(ast_to_sexp(&parse("let add = (x y) { (+ x y) }")[0]), "(let add (fn (x y) (scope (parentheses (variable-ref +) (variable-ref x) (variable-ref y)))))");
+    }
+
+    #[test]
+    fn program_has_one_node_per_line() {
+        assert_eq!(ast_list_to_sexp(&parse("let s = \"hi\"\n3.14")), "(program\n  (let s (string \"hi\"))\n  (float 3.14))");
+        assert_eq!(ast_list_to_sexp(&[]), "(program)");
+    }
+}
@@ -0,0 +1,70 @@
+use crate::AST::*;
+use crate::type_inference::*;
+
+/// Type checks every top-level node, collecting all of the errors instead of stopping at the first one
+pub fn type_check_ast(ast: &[ASTNode]) -> Result<(), Vec<TypeError>> {
+    let mut env = TypeEnv::new();
+    let mut errors = vec!();
+    for node in ast {
+        if let Err(err) = infer(node, &mut env) {
+            errors.push(err);
+            // If a declaration fails to type check, the name still gets bound to an unknown type.
+            // Otherwise every later use of it would be reported as an undefined variable on top of the real error.
+            if let ASTExpression::ASTInitialization(name, _) = &node.expression {
+                let unknown_type = env.fresh_var();
+                env.bindings.insert(name.clone(), unknown_type);
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_source(text: &str) -> Result<(), Vec<TypeError>> {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        type_check_ast(&result.nodes)
+    }
+
+    #[test]
+    fn applying_a_non_function_is_an_error() {
+        let errors = check_source("let x = 0\n(x \"hello\")").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 2));
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        let errors = check_source("(1 2)\nlet y = 1\nif true then y else \"a\"").unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn well_typed_program_passes() {
+        assert!(check_source("let x = 0\n(+ x 1)").is_ok());
+    }
+    #[test]
+    fn operators_work_on_floats_and_strings() {
+        assert!(check_source("let z = (+ 1.5 2.5)\nlet n = (+ 1 2)").is_ok());
+        assert!(check_source("let s = \"a\" + \"b\"\nlet same = \"a\" == \"b\"").is_ok());
+        assert!(check_source("let x = -1.5\nlet y = -1").is_ok());
+    }
+
+    #[test]
+    fn operator_sides_must_match() {
+        let errors = check_source("(+ 1 \"a\")").unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn tuples_and_strings_can_be_used() {
+        assert!(check_source("let pair = (1, \"a\")\nfor c in \"abc\" { c }").is_ok());
+    }
+}
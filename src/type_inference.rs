@@ -0,0 +1,449 @@
+use std::{collections::HashMap, fmt};
+use crate::AST::*;
+use crate::eval::BUILTIN_OPERATORS;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Str,
+    Char,
+    Bool,
+    Unit,
+    Fun(Box<Type>, Box<Type>),
+    List(Box<Type>),
+    Tuple(Vec<Type>),
+    Var(u32),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::Str => write!(f, "Str"),
+            Type::Char => write!(f, "Char"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Unit => write!(f, "Unit"),
+            // Function types nest to the right, so only a function on the left side needs parentheses.
+            Type::Fun(parameter, result) => match **parameter {
+                Type::Fun(_, _) => write!(f, "({}) -> {}", parameter, result),
+                _ => write!(f, "{} -> {}", parameter, result),
+            },
+            Type::List(element) => write!(f, "[{}]", element),
+            Type::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(f, "({})", elements.join(", "))
+            },
+            Type::Var(id) => write!(f, "t{}", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl TypeError {
+    pub fn new(message: String, location: ASTLocation) -> Self {
+        TypeError {
+            message,
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+/// Holds the types of the variables in scope, along with everything unification has learned about the type variables so far
+#[derive(Debug, Clone, Default)]
+pub struct TypeEnv {
+    pub bindings: HashMap<String, Type>,
+    // The types of the builtin operators. Their type variables are generic, so each use of an operator gets its own fresh copy of them.
+    // That way '(+ 1 2)' and '(+ 1.5 2.5)' can both be in the same program.
+    builtins: HashMap<String, Type>,
+    substitution: HashMap<u32, Type>,
+    next_var: u32,
+}
+
+impl TypeEnv {
+    /// Creates an environment that already knows the types of the builtin operators.
+    /// Arithmetic operators are 'a -> a -> a' and comparisons are 'a -> a -> Bool', since they work on integers, floats, and strings alike.
+    pub fn new() -> Self {
+        let mut env = TypeEnv::default();
+        let operand_type = Type::Var(0);
+        for operator in BUILTIN_OPERATORS {
+            let result_type = if ["+", "-", "*", "/", "%", "**"].contains(operator) { operand_type.clone() } else { Type::Bool };
+            let operator_type = Type::Fun(Box::new(operand_type.clone()), Box::new(Type::Fun(Box::new(operand_type.clone()), Box::new(result_type))));
+            env.builtins.insert(operator.to_string(), operator_type);
+        }
+        env
+    }
+
+    pub fn fresh_var(&mut self) -> Type {
+        let var = Type::Var(self.next_var);
+        self.next_var += 1;
+        var
+    }
+
+    /// Replaces every solved type variable in a type with what it was solved to
+    pub fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(id) => match self.substitution.get(id) {
+                Some(solved) => self.resolve(solved),
+                None => ty.clone(),
+            },
+            Type::Fun(parameter, result) => Type::Fun(Box::new(self.resolve(parameter)), Box::new(self.resolve(result))),
+            Type::List(element) => Type::List(Box::new(self.resolve(element))),
+            Type::Tuple(elements) => Type::Tuple(elements.iter().map(|element| self.resolve(element)).collect()),
+            _ => ty.clone(),
+        }
+    }
+
+    // Gives every type variable in a builtin's type a fresh variable, keeping the ones that appear more than once the same.
+    fn instantiate(&mut self, ty: &Type, fresh_vars: &mut HashMap<u32, Type>) -> Type {
+        match ty {
+            Type::Var(id) => match fresh_vars.get(id) {
+                Some(fresh) => fresh.clone(),
+                None => {
+                    let fresh = self.fresh_var();
+                    fresh_vars.insert(*id, fresh.clone());
+                    fresh
+                },
+            },
+            Type::Fun(parameter, result) => Type::Fun(Box::new(self.instantiate(parameter, fresh_vars)), Box::new(self.instantiate(result, fresh_vars))),
+            Type::List(element) => Type::List(Box::new(self.instantiate(element, fresh_vars))),
+            Type::Tuple(elements) => Type::Tuple(elements.iter().map(|element| self.instantiate(element, fresh_vars)).collect()),
+            _ => ty.clone(),
+        }
+    }
+
+    /// Looks up a variable's type, with any solved type variables filled in.
+    /// Builtin operators that haven't been shadowed get a fresh copy of their type every time.
+    pub fn lookup(&mut self, name: &str) -> Option<Type> {
+        if let Some(ty) = self.bindings.get(name) {
+            return Some(self.resolve(ty));
+        }
+        let builtin_type = self.builtins.get(name)?.clone();
+        Some(self.instantiate(&builtin_type, &mut HashMap::new()))
+    }
+
+    fn occurs(&self, id: u32, ty: &Type) -> bool {
+        match self.resolve(ty) {
+            Type::Var(other_id) => id == other_id,
+            Type::Fun(parameter, result) => self.occurs(id, &parameter) || self.occurs(id, &result),
+            Type::List(element) => self.occurs(id, &element),
+            Type::Tuple(elements) => elements.iter().any(|element| self.occurs(id, element)),
+            _ => false,
+        }
+    }
+
+    /// Robinson unification. The occurs check stops a variable from being solved to a type that contains itself (e.g. t0 = t0 -> Int).
+    pub fn unify(&mut self, a: &Type, b: &Type) -> Result<(), String> {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Var(a_id), Type::Var(b_id)) if a_id == b_id => Ok(()),
+            (Type::Var(id), other) | (other, Type::Var(id)) => {
+                if self.occurs(id, &other) {
+                    Err(format!("Infinite type: t{} occurs in {}", id, other))
+                } else {
+                    self.substitution.insert(id, other);
+                    Ok(())
+                }
+            },
+            (Type::Fun(a_parameter, a_result), Type::Fun(b_parameter, b_result)) => {
+                self.unify(&a_parameter, &b_parameter)?;
+                self.unify(&a_result, &b_result)
+            },
+            (Type::List(a_element), Type::List(b_element)) => self.unify(&a_element, &b_element),
+            (Type::Tuple(a_elements), Type::Tuple(b_elements)) if a_elements.len() == b_elements.len() => {
+                for (a_element, b_element) in a_elements.iter().zip(&b_elements) {
+                    self.unify(a_element, b_element)?;
+                }
+                Ok(())
+            },
+            (a, b) if a == b => Ok(()),
+            (a, b) => Err(format!("Type mismatch: expected {}, but found {}", a, b)),
+        }
+    }
+}
+
+// Scopes get a copy of the bindings, so anything declared inside of them is gone once they end.
+// The substitution is kept though, since solving a type variable inside a scope should be seen outside of it too.
+fn infer_in_scope(nodes: &[ASTNode], env: &mut TypeEnv) -> Result<Type, TypeError> {
+    let outer_bindings = env.bindings.clone();
+    let mut last_type = Type::Unit;
+    for node in nodes {
+        match infer(node, env) {
+            Ok(ty) => last_type = ty,
+            Err(err) => {
+                env.bindings = outer_bindings;
+                return Err(err);
+            }
+        }
+    }
+    env.bindings = outer_bindings;
+    Ok(last_type)
+}
+
+pub fn infer(node: &ASTNode, env: &mut TypeEnv) -> Result<Type, TypeError> {
+    let location = node.location;
+    let ty = match &node.expression {
+        ASTExpression::ASTInteger(_) => Type::Int,
+        ASTExpression::ASTFloat(_) | ASTExpression::ASTFloat32(_) => Type::Float,
+        ASTExpression::ASTString(_) => Type::Str,
+        ASTExpression::ASTChar(_) => Type::Char,
+        ASTExpression::ASTBool(_) => Type::Bool,
+        ASTExpression::ASTInterpolation(parts) => {
+            for part in parts {
+                infer(part, env)?;
+            }
+            Type::Str
+        },
+        ASTExpression::ASTNull | ASTExpression::ASTUnit => Type::Unit,
+        ASTExpression::ASTVariableRef(name) => {
+            env.lookup(name).ok_or(TypeError::new(format!("Undefined variable '{}'", name), location))?
+        },
+        ASTExpression::ASTInitialization(name, value) => {
+            let value_type = infer(value, env)?;
+            env.bindings.insert(name.clone(), value_type.clone());
+            value_type
+        },
+        // The name gets a placeholder type while the function is inferred, which is then unified with the function's real type.
+        ASTExpression::ASTLetRec(name, value) => {
+            let placeholder_type = env.fresh_var();
+            env.bindings.insert(name.clone(), placeholder_type.clone());
+            let value_type = infer(value, env)?;
+            env.unify(&placeholder_type, &value_type).map_err(|message| TypeError::new(format!("Recursive use of '{}' does not match its definition: {}", name, message), location))?;
+            value_type
+        },
+        ASTExpression::ASTAssignment(name, value) => {
+            let value_type = infer(value, env)?;
+            match env.lookup(name) {
+                Some(existing_type) => {
+                    env.unify(&existing_type, &value_type).map_err(|message| TypeError::new(format!("Cannot assign to '{}': {}", name, message), location))?;
+                },
+                None => {
+                    env.bindings.insert(name.clone(), value_type.clone());
+                }
+            }
+            value_type
+        },
+        ASTExpression::ASTScope(nodes) => infer_in_scope(nodes, env)?,
+        // A single expression in parentheses is just grouping, but with more than one, the first is applied to the rest.
+        ASTExpression::ASTParentheses(nodes) => match nodes.split_first() {
+            None => Type::Unit,
+            Some((only, [])) => infer(only, env)?,
+            Some((function, arguments)) => {
+                let mut function_type = infer(function, env)?;
+                for argument in arguments {
+                    if !matches!(env.resolve(&function_type), Type::Fun(_, _) | Type::Var(_)) {
+                        return Err(TypeError::new(format!("Cannot apply a value of type {} as a function", env.resolve(&function_type)), function.location));
+                    }
+                    let argument_type = infer(argument, env)?;
+                    let result_type = env.fresh_var();
+                    let expected_type = Type::Fun(Box::new(argument_type), Box::new(result_type.clone()));
+                    env.unify(&function_type, &expected_type).map_err(|message| TypeError::new(message, argument.location))?;
+                    function_type = result_type;
+                }
+                function_type
+            },
+        },
+        // Functions with more than one parameter are curried, so '(x y) { ... }' has the type 'x -> y -> ...'.
+        ASTExpression::ASTFunction(parameters, body) => {
+            let parameter_names = function_parameter_names(parameters)?;
+            let outer_bindings = env.bindings.clone();
+            let mut parameter_types = vec!();
+            for name in parameter_names {
+                let parameter_type = env.fresh_var();
+                env.bindings.insert(name, parameter_type.clone());
+                parameter_types.push(parameter_type);
+            }
+            let body_type = infer(body, env);
+            env.bindings = outer_bindings;
+            let mut function_type = body_type?;
+            for parameter_type in parameter_types.into_iter().rev() {
+                function_type = Type::Fun(Box::new(parameter_type), Box::new(function_type));
+            }
+            function_type
+        },
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+            let condition_type = infer(condition, env)?;
+            env.unify(&Type::Bool, &condition_type).map_err(|message| TypeError::new(format!("If conditions must be booleans: {}", message), condition.location))?;
+            let then_type = infer(then_branch, env)?;
+            let else_type = infer(else_branch, env)?;
+            env.unify(&then_type, &else_type).map_err(|message| TypeError::new(format!("Both branches of an if must have the same type: {}", message), else_branch.location))?;
+            then_type
+        },
+        ASTExpression::ASTWhile(condition, body) => {
+            let condition_type = infer(condition, env)?;
+            env.unify(&Type::Bool, &condition_type).map_err(|message| TypeError::new(format!("While conditions must be booleans: {}", message), condition.location))?;
+            infer(body, env)?;
+            Type::Unit
+        },
+        // Infix operators are typed like calling the operator with both sides, e.g. '1 + 2' like '(+ 1 2)'.
+        ASTExpression::ASTBinaryOp(left, operator, right) => {
+            let operator_type = match operator.as_str() {
+                "&&" | "||" => Type::Fun(Box::new(Type::Bool), Box::new(Type::Fun(Box::new(Type::Bool), Box::new(Type::Bool)))),
+                _ => env.lookup(operator).ok_or(TypeError::new(format!("Unknown operator '{}'", operator), location))?,
+            };
+            let left_type = infer(left, env)?;
+            let right_type = infer(right, env)?;
+            let result_type = env.fresh_var();
+            let expected_type = Type::Fun(Box::new(left_type), Box::new(Type::Fun(Box::new(right_type), Box::new(result_type.clone()))));
+            env.unify(&operator_type, &expected_type).map_err(|message| TypeError::new(message, location))?;
+            result_type
+        },
+        // '!' only works on booleans, but '-' works on both integers and floats, so its operand type is left open.
+        ASTExpression::ASTUnaryOp(operator, operand) => {
+            let operand_type = infer(operand, env)?;
+            let expected_type = if operator == "!" { Type::Bool } else { env.fresh_var() };
+            env.unify(&expected_type, &operand_type).map_err(|message| TypeError::new(message, location))?;
+            expected_type
+        },
+        // Looping over a string goes through its characters.
+        ASTExpression::ASTForIn(name, iterable, body) => {
+            let iterable_type = infer(iterable, env)?;
+            let element_type =
+                if env.resolve(&iterable_type) == Type::Str {
+                    Type::Char
+                } else {
+                    let element_type = env.fresh_var();
+                    env.unify(&Type::List(Box::new(element_type.clone())), &iterable_type).map_err(|message| TypeError::new(format!("For loops can only loop over lists and strings: {}", message), iterable.location))?;
+                    element_type
+                };
+            let outer_bindings = env.bindings.clone();
+            env.bindings.insert(name.clone(), element_type);
+            let body_type = infer(body, env);
+            env.bindings = outer_bindings;
+            body_type?;
+            Type::Unit
+        },
+        ASTExpression::ASTRange { start, end, .. } => {
+            for bound in [start, end] {
+                let bound_type = infer(bound, env)?;
+                env.unify(&Type::Int, &bound_type).map_err(|message| TypeError::new(format!("Range bounds must be integers: {}", message), bound.location))?;
+            }
+            Type::List(Box::new(Type::Int))
+        },
+        ASTExpression::ASTModule(_, body) | ASTExpression::ASTTraitDef(_, body) | ASTExpression::ASTImpl { body, .. } => {
+            infer_in_scope(body, env)?;
+            Type::Unit
+        },
+        ASTExpression::ASTImport { .. } | ASTExpression::ASTStructDef(_, _) | ASTExpression::ASTEnumDef(_, _) | ASTExpression::ASTDocComment(_) => Type::Unit,
+        // Every element has to have the same type. An empty list's element type is left open.
+        ASTExpression::ASTList(nodes) => {
+            let element_type = env.fresh_var();
+            for node in nodes {
+                let node_type = infer(node, env)?;
+                env.unify(&element_type, &node_type).map_err(|message| TypeError::new(format!("List elements must all have the same type: {}", message), node.location))?;
+            }
+            Type::List(Box::new(element_type))
+        },
+        ASTExpression::ASTTuple(nodes) => Type::Tuple(nodes.iter().map(|node| infer(node, env)).collect::<Result<Vec<Type>, TypeError>>()?),
+        ASTExpression::ASTIndex(target, index) => {
+            let element_type = env.fresh_var();
+            let target_type = infer(target, env)?;
+            env.unify(&Type::List(Box::new(element_type.clone())), &target_type).map_err(|message| TypeError::new(format!("Only lists can be indexed: {}", message), target.location))?;
+            let index_type = infer(index, env)?;
+            env.unify(&Type::Int, &index_type).map_err(|message| TypeError::new(format!("Indexes must be integers: {}", message), index.location))?;
+            element_type
+        },
+        // Every pattern has to fit the type of the value, and every arm has to have the same type.
+        ASTExpression::ASTMatch(value, arms) => {
+            let value_type = infer(value, env)?;
+            let result_type = env.fresh_var();
+            for (pattern, body) in arms {
+                let outer_bindings = env.bindings.clone();
+                match &pattern.expression {
+                    ASTExpression::ASTVariableRef(name) => {
+                        env.bindings.insert(name.clone(), value_type.clone());
+                    },
+                    ASTExpression::ASTWildcard => {},
+                    _ => {
+                        let pattern_type = infer(pattern, env)?;
+                        env.unify(&value_type, &pattern_type).map_err(|message| TypeError::new(format!("Patterns must have the same type as the value being matched: {}", message), pattern.location))?;
+                    },
+                }
+                let body_type = infer(body, env);
+                env.bindings = outer_bindings;
+                env.unify(&result_type, &body_type?).map_err(|message| TypeError::new(format!("Every match arm must have the same type: {}", message), body.location))?;
+            }
+            result_type
+        },
+        // The value leaves the function, so the return expression itself can fit in anywhere.
+        ASTExpression::ASTReturn(value) => {
+            infer(value, env)?;
+            env.fresh_var()
+        },
+        ASTExpression::ASTBreak | ASTExpression::ASTContinue => env.fresh_var(),
+        ASTExpression::ASTWildcard => {
+            return Err(TypeError::new(String::from("'_' can only be used as a pattern"), location));
+        },
+        ASTExpression::ASTFieldAccess(_, _) => {
+            return Err(TypeError::new(String::from("Type inference does not support field access yet"), location));
+        },
+    };
+    Ok(env.resolve(&ty))
+}
+
+/// Gets the names out of a function's parameter list, which has to be a set of parentheses holding only variable names
+pub fn function_parameter_names(parameters: &ASTNode) -> Result<Vec<String>, TypeError> {
+    match &parameters.expression {
+        ASTExpression::ASTParentheses(nodes) => nodes.iter().map(|node| match &node.expression {
+            ASTExpression::ASTVariableRef(name) => Ok(name.clone()),
+            _ => Err(TypeError::new(String::from("Function parameters must be variable names"), node.location)),
+        }).collect(),
+        _ => Err(TypeError::new(String::from("Expected a parameter list"), parameters.location)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Infers every top-level node in order, returning the type of the last one.
+    fn infer_source(text: &str, env: &mut TypeEnv) -> Result<Type, TypeError> {
+        let result = parse_file(text.to_string(), None);
+        assert!(result.errors.is_empty(), "unexpected parse errors: {:?}", result.errors);
+        let mut last_type = Type::Unit;
+        for node in &result.nodes {
+            last_type = infer(node, env)?;
+        }
+        Ok(env.resolve(&last_type))
+    }
+
+    #[test]
+    fn let_binds_the_value_type() {
+        let mut env = TypeEnv::new();
+        assert_eq!(infer_source("let x = 0", &mut env).unwrap(), Type::Int);
+        assert_eq!(env.lookup("x"), Some(Type::Int));
+    }
+
+    #[test]
+    fn identity_function_is_generic() {
+        let ty = infer_source("(x) { x }", &mut TypeEnv::new()).unwrap();
+        assert_eq!(ty, Type::Fun(Box::new(Type::Var(0)), Box::new(Type::Var(0))));
+    }
+
+    #[test]
+    fn occurs_check_rejects_infinite_types() {
+        let err = infer_source("(f) { (f f) }", &mut TypeEnv::new()).unwrap_err();
+        assert!(err.message.starts_with("Infinite type"), "{}", err.message);
+    }
+    #[test]
+    fn tuple_has_a_type_for_each_element() {
+        let ty = infer_source("(1, \"a\", 2.5)", &mut TypeEnv::new()).unwrap();
+        assert_eq!(ty, Type::Tuple(vec!(Type::Int, Type::Str, Type::Float)));
+        assert_eq!(ty.to_string(), "(Int, Str, Float)");
+    }
+
+    #[test]
+    fn each_operator_use_gets_its_own_type() {
+        let mut env = TypeEnv::new();
+        assert_eq!(infer_source("(+ 1 2)", &mut env).unwrap(), Type::Int);
+        assert_eq!(infer_source("(+ 1.5 2.5)", &mut env).unwrap(), Type::Float);
+        assert_eq!(infer_source("(< \"a\" \"b\")", &mut env).unwrap(), Type::Bool);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::AST::*;
+
+/// Gets called back for each node as an AST is walked with ASTNode::accept.
+/// Every method does nothing by default, so a visitor only needs to implement the ones it cares about.
+pub trait ASTVisitor {
+    // Called for every node, before the more specific method for that kind of node.
+    fn visit_node(&mut self, _node: &ASTNode) {}
+    fn visit_integer(&mut self, _value: i64) {}
+    // Called for both 64 and 32 bit floats.
+    fn visit_float(&mut self, _value: f64) {}
+    fn visit_string(&mut self, _value: &str) {}
+    fn visit_bool(&mut self, _value: bool) {}
+    fn visit_variable_ref(&mut self, _name: &str) {}
+    // Called for plain assignments, 'let' initializations, and 'let rec' definitions.
+    fn visit_assignment(&mut self, _name: &str, _value: &ASTNode) {}
+    fn visit_scope(&mut self, _nodes: &[ASTNode]) {}
+    fn visit_parentheses(&mut self, _nodes: &[ASTNode]) {}
+    fn visit_function(&mut self, _parameters: &ASTNode, _body: &ASTNode) {}
+    fn visit_null(&mut self) {}
+}
+
+impl ASTNode {
+    /// Walks this node and everything under it, calling the visitor on each node before its children
+    pub fn accept(&self, visitor: &mut dyn ASTVisitor) {
+        visitor.visit_node(self);
+        match &self.expression {
+            ASTExpression::ASTInteger(x) => visitor.visit_integer(*x),
+            ASTExpression::ASTFloat(x) => visitor.visit_float(*x),
+            ASTExpression::ASTFloat32(x) => visitor.visit_float(*x as f64),
+            ASTExpression::ASTString(x) => visitor.visit_string(x),
+            ASTExpression::ASTBool(x) => visitor.visit_bool(*x),
+            ASTExpression::ASTVariableRef(name) => visitor.visit_variable_ref(name),
+            ASTExpression::ASTNull => visitor.visit_null(),
+            ASTExpression::ASTAssignment(name, value) | ASTExpression::ASTInitialization(name, value) | ASTExpression::ASTLetRec(name, value) => {
+                visitor.visit_assignment(name, value);
+                value.accept(visitor);
+            },
+            ASTExpression::ASTScope(nodes) => {
+                visitor.visit_scope(nodes);
+                accept_all(nodes, visitor);
+            },
+            ASTExpression::ASTParentheses(nodes) => {
+                visitor.visit_parentheses(nodes);
+                accept_all(nodes, visitor);
+            },
+            ASTExpression::ASTFunction(parameters, body) => {
+                visitor.visit_function(parameters, body);
+                parameters.accept(visitor);
+                body.accept(visitor);
+            },
+            ASTExpression::ASTInterpolation(nodes)
+            | ASTExpression::ASTTuple(nodes)
+            | ASTExpression::ASTList(nodes)
+            | ASTExpression::ASTModule(_, nodes)
+            | ASTExpression::ASTTraitDef(_, nodes)
+            | ASTExpression::ASTImpl { body: nodes, .. } => accept_all(nodes, visitor),
+            ASTExpression::ASTRange { start, end, .. } => {
+                start.accept(visitor);
+                end.accept(visitor);
+            },
+            ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+                condition.accept(visitor);
+                then_branch.accept(visitor);
+                else_branch.accept(visitor);
+            },
+            ASTExpression::ASTWhile(condition, body) => {
+                condition.accept(visitor);
+                body.accept(visitor);
+            },
+            ASTExpression::ASTForIn(_, iterable, body) => {
+                iterable.accept(visitor);
+                body.accept(visitor);
+            },
+            ASTExpression::ASTBinaryOp(left, _, right) | ASTExpression::ASTIndex(left, right) => {
+                left.accept(visitor);
+                right.accept(visitor);
+            },
+            ASTExpression::ASTUnaryOp(_, operand) | ASTExpression::ASTFieldAccess(operand, _) | ASTExpression::ASTReturn(operand) => operand.accept(visitor),
+            ASTExpression::ASTMatch(value, arms) => {
+                value.accept(visitor);
+                for (pattern, body) in arms {
+                    pattern.accept(visitor);
+                    body.accept(visitor);
+                }
+            },
+            ASTExpression::ASTChar(_)
+            | ASTExpression::ASTDocComment(_)
+            | ASTExpression::ASTImport { .. }
+            | ASTExpression::ASTWildcard
+            | ASTExpression::ASTBreak
+            | ASTExpression::ASTContinue
+            | ASTExpression::ASTStructDef(_, _)
+            | ASTExpression::ASTEnumDef(_, _)
+            | ASTExpression::ASTUnit => {},
+        }
+    }
+}
+
+/// Walks every node in a list with ASTNode::accept, in order
+pub fn accept_all(nodes: &[ASTNode], visitor: &mut dyn ASTVisitor) {
+    for node in nodes {
+        node.accept(visitor);
+    }
+}
+
+/// Counts every node in an AST
+#[derive(Debug, Default)]
+pub struct NodeCounter {
+    pub count: usize,
+}
+
+impl ASTVisitor for NodeCounter {
+    fn visit_node(&mut self, _node: &ASTNode) {
+        self.count += 1;
+    }
+}
+
+/// Calls f on every node in the tree, going depth first and visiting each node before its children
+pub fn walk_depth_first(node: &ASTNode, f: &mut dyn FnMut(&ASTNode)) {
+    f(node);
+    for child in node.expression.children() {
+        walk_depth_first(child, f);
+    }
+}
+
+/// Calls f on every node in the tree one level at a time, so a node's children are visited after everything at its own depth
+#[cfg(test)]
+pub fn walk_breadth_first(node: &ASTNode, f: &mut dyn FnMut(&ASTNode)) {
+    let mut queue = std::collections::VecDeque::from([node]);
+    while let Some(node) = queue.pop_front() {
+        f(node);
+        queue.extend(node.expression.children());
+    }
+}
+
+/// Like walk_depth_first, but lets f change each node in place.
+/// The children that get walked are the ones the node has after f is called on it.
+#[cfg(test)]
+pub fn walk_depth_first_mut(node: &mut ASTNode, f: &mut dyn FnMut(&mut ASTNode)) {
+    f(node);
+    for child in node.expression.children_mut() {
+        walk_depth_first_mut(child, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::*;
+
+    fn parse(text: &str) -> Vec<ASTNode> {
+        let ParseFileResult { nodes, errors } = parse_file(text.to_string(), None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        nodes
+    }
+
+    #[test]
+    fn node_counter_counts_every_node() {
+        let mut counter = NodeCounter::default();
+        accept_all(&parse("{ let x = 3 x }\n(+ 1 2)"), &mut counter);
+        // The scope, the let, its value, and the reference, then the parentheses and their 3 children.
+        assert_eq!(counter.count, 8);
+    }
+
+    // Collects the name of every variable that is referenced in an AST.
+    #[derive(Debug, Default)]
+    struct VariableCollector {
+        names: HashSet<String>,
+    }
+
+    impl ASTVisitor for VariableCollector {
+        fn visit_variable_ref(&mut self, name: &str) {
+            self.names.insert(name.to_string());
+        }
+    }
+
+    #[test]
+    fn variable_collector_finds_nested_references() {
+        let mut collector = VariableCollector::default();
+        accept_all(&parse("let x = (f y)\nif x then [z] else (g) { g }"), &mut collector);
+        let expected: HashSet<String> = ["f", "y", "x", "z", "g"].iter().map(|name| name.to_string()).collect();
+        assert_eq!(collector.names, expected);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        visited: Vec<String>,
+    }
+
+    impl ASTVisitor for Recorder {
+        fn visit_integer(&mut self, value: i64) {
+            self.visited.push(value.to_string());
+        }
+
+        fn visit_assignment(&mut self, name: &str, _value: &ASTNode) {
+            self.visited.push(name.to_string());
+        }
+    }
+
+    #[test]
+    fn nodes_are_visited_before_their_children() {
+        let mut recorder = Recorder::default();
+        accept_all(&parse("let a = (1 (let b = 2) 3)"), &mut recorder);
+        assert_eq!(recorder.visited, vec!("a", "1", "b", "2", "3"));
+    }
+
+    #[test]
+    fn depth_first_visits_parents_before_children() {
+        let nodes = parse("(0 (1 (2)) 3)");
+        let mut visited = vec!();
+        walk_depth_first(&nodes[0], &mut |node| visited.push(node.to_string()));
+        assert_eq!(visited, vec!("(0 (1 (2)) 3)", "0", "(1 (2))", "1", "(2)", "2", "3"));
+    }
+
+    #[test]
+    fn breadth_first_visits_one_level_at_a_time() {
+        let nodes = parse("(0 (1 (2)) 3)");
+        let mut visited = vec!();
+        walk_breadth_first(&nodes[0], &mut |node| visited.push(node.to_string()));
+        assert_eq!(visited, vec!("(0 (1 (2)) 3)", "0", "(1 (2))", "3", "1", "(2)", "2"));
+    }
+
+    #[test]
+    fn depth_first_mut_changes_nodes_in_place() {
+        let mut nodes = parse("(0 (1 (2)) 3)");
+        walk_depth_first_mut(&mut nodes[0], &mut |node| {
+            if let ASTExpression::ASTInteger(x) = &mut node.expression {
+                *x += 10;
+            }
+        });
+        assert_eq!(nodes[0].to_string(), "(10 (11 (12)) 13)");
+    }
+}
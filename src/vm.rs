@@ -0,0 +1,373 @@
+use std::{collections::HashMap, fmt};
+use crate::AST::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Char(char),
+    Bool(bool),
+    // A function's parameter name, the address of its body, and the local variables it captured when it was made.
+    Fun(String, usize, HashMap<String, Value>),
+    Unit,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(x) => write!(f, "{}", x),
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Str(x) => write!(f, "{}", x),
+            Value::Char(x) => write!(f, "{}", x),
+            Value::Bool(x) => write!(f, "{}", x),
+            Value::Fun(parameter, address, _) => write!(f, "<function ({}) at {}>", parameter, address),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Push(Value),
+    Pop,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Load(String),
+    // Stores the value on top of the stack without popping it, since assignments are expressions too.
+    Store(String),
+    // Calls the function under the arguments with that many arguments (zero or one, since functions are curried).
+    Call(usize),
+    Return,
+    // Skips the next 'usize' instructions. Jumps are relative so compiled code can be moved around and appended together.
+    Jump(usize),
+    // Pops a boolean and skips the next 'usize' instructions if it is false.
+    JumpIfFalse(usize),
+    // Makes a function with the given parameter name out of the next 'usize' instructions, then skips past them.
+    MakeFunc(String, usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct VmError {
+    pub message: String,
+}
+
+impl VmError {
+    pub fn new(message: String) -> Self {
+        VmError { message }
+    }
+}
+
+// Compiles a curried function, so '(x y) { ... }' becomes a function of x whose body makes a function of y.
+fn compile_function(parameter_names: &[String], body: &ASTNode) -> Result<Vec<Instruction>, VmError> {
+    let (first, rest) = match parameter_names.split_first() {
+        Some((first, rest)) => (first.clone(), rest),
+        None => (String::new(), &[] as &[String]),
+    };
+    let mut body_code =
+        if rest.is_empty() {
+            compile(body)?
+        } else {
+            compile_function(rest, body)?
+        };
+    body_code.push(Instruction::Return);
+    let mut output = vec!(Instruction::MakeFunc(first, body_code.len()));
+    output.append(&mut body_code);
+    Ok(output)
+}
+
+// Compiles a list of expressions where only the last value is kept, like the body of a scope.
+fn compile_sequence(nodes: &[&ASTNode]) -> Result<Vec<Instruction>, VmError> {
+    let mut output = vec!();
+    for (index, node) in nodes.iter().enumerate() {
+        output.append(&mut compile(node)?);
+        if index + 1 < nodes.len() {
+            output.push(Instruction::Pop);
+        }
+    }
+    if nodes.is_empty() {
+        output.push(Instruction::Push(Value::Unit));
+    }
+    Ok(output)
+}
+
+// The operators that have their own instruction
+fn operator_instruction(operator: &str) -> Option<Instruction> {
+    match operator {
+        "+" => Some(Instruction::Add),
+        "-" => Some(Instruction::Sub),
+        "*" => Some(Instruction::Mul),
+        "/" => Some(Instruction::Div),
+        _ => None,
+    }
+}
+
+// Moves the program counter forward past some instructions.
+// Bytecode loaded from a file can have any offset in it, so one that is too big is an error instead of an overflow.
+fn skip(pc: usize, count: usize) -> Result<usize, VmError> {
+    pc.checked_add(count).ok_or(VmError::new(format!("Cannot skip {} instructions from {}", count, pc)))
+}
+
+pub fn compile(node: &ASTNode) -> Result<Vec<Instruction>, VmError> {
+    let output = match &node.expression {
+        ASTExpression::ASTInteger(x) => vec!(Instruction::Push(Value::Int(*x))),
+        ASTExpression::ASTFloat(x) => vec!(Instruction::Push(Value::Float(*x))),
+        ASTExpression::ASTFloat32(x) => vec!(Instruction::Push(Value::Float(*x as f64))),
+        ASTExpression::ASTString(x) => vec!(Instruction::Push(Value::Str(x.clone()))),
+        ASTExpression::ASTChar(x) => vec!(Instruction::Push(Value::Char(*x))),
+        ASTExpression::ASTBool(x) => vec!(Instruction::Push(Value::Bool(*x))),
+        ASTExpression::ASTNull | ASTExpression::ASTUnit => vec!(Instruction::Push(Value::Unit)),
+        ASTExpression::ASTVariableRef(name) => vec!(Instruction::Load(name.clone())),
+        ASTExpression::ASTInitialization(name, value) | ASTExpression::ASTAssignment(name, value) | ASTExpression::ASTLetRec(name, value) => {
+            let mut output = compile(value)?;
+            output.push(Instruction::Store(name.clone()));
+            output
+        },
+        ASTExpression::ASTScope(nodes) => {
+            let nodes: Vec<&ASTNode> = nodes.iter().collect();
+            compile_sequence(&nodes)?
+        },
+        ASTExpression::ASTFunction(parameters, body) => {
+            let parameter_names = match &parameters.expression {
+                ASTExpression::ASTParentheses(nodes) => nodes.iter().map(|node| match &node.expression {
+                    ASTExpression::ASTVariableRef(name) => Ok(name.clone()),
+                    _ => Err(VmError::new(String::from("Function parameters must be variable names"))),
+                }).collect::<Result<Vec<String>, VmError>>()?,
+                _ => return Err(VmError::new(String::from("Expected a parameter list"))),
+            };
+            compile_function(&parameter_names, body)?
+        },
+        ASTExpression::ASTParentheses(nodes) => match nodes.split_first() {
+            None => vec!(Instruction::Push(Value::Unit)),
+            Some((only, [])) => compile(only)?,
+            Some((function, arguments)) => {
+                let operator_instruction = match &function.expression {
+                    ASTExpression::ASTVariableRef(name) => operator_instruction(name),
+                    _ => None,
+                };
+                let mut output = vec!();
+                match operator_instruction {
+                    Some(instruction) if arguments.len() == 2 => {
+                        output.append(&mut compile(&arguments[0])?);
+                        output.append(&mut compile(&arguments[1])?);
+                        output.push(instruction);
+                    },
+                    _ => {
+                        output.append(&mut compile(function)?);
+                        for argument in arguments {
+                            output.append(&mut compile(argument)?);
+                            output.push(Instruction::Call(1));
+                        }
+                    },
+                }
+                output
+            },
+        },
+        ASTExpression::ASTBinaryOp(left, operator, right) => {
+            let instruction = operator_instruction(operator).ok_or(VmError::new(format!("The VM does not support the '{}' operator yet (line: {}, column: {})", operator, node.location.line, node.location.column)))?;
+            let mut output = compile(left)?;
+            output.append(&mut compile(right)?);
+            output.push(instruction);
+            output
+        },
+        ASTExpression::ASTIfElse(condition, then_branch, else_branch) => {
+            let mut then_code = compile(then_branch)?;
+            let mut else_code = compile(else_branch)?;
+            then_code.push(Instruction::Jump(else_code.len()));
+            let mut output = compile(condition)?;
+            output.push(Instruction::JumpIfFalse(then_code.len()));
+            output.append(&mut then_code);
+            output.append(&mut else_code);
+            output
+        },
+        ASTExpression::ASTImport { .. } | ASTExpression::ASTDocComment(_) => vec!(Instruction::Push(Value::Unit)),
+        ASTExpression::ASTInterpolation(_)
+        | ASTExpression::ASTTuple(_)
+        | ASTExpression::ASTList(_)
+        | ASTExpression::ASTUnaryOp(_, _)
+        | ASTExpression::ASTFieldAccess(_, _)
+        | ASTExpression::ASTIndex(_, _)
+        | ASTExpression::ASTMatch(_, _)
+        | ASTExpression::ASTReturn(_)
+        | ASTExpression::ASTBreak
+        | ASTExpression::ASTContinue
+        | ASTExpression::ASTWildcard
+        | ASTExpression::ASTWhile(_, _)
+        | ASTExpression::ASTForIn(_, _, _)
+        | ASTExpression::ASTRange { .. }
+        | ASTExpression::ASTModule(_, _)
+        | ASTExpression::ASTTraitDef(_, _)
+        | ASTExpression::ASTStructDef(_, _)
+        | ASTExpression::ASTEnumDef(_, _)
+        | ASTExpression::ASTImpl { .. } => {
+            return Err(VmError::new(format!("The VM does not support this expression yet (line: {}, column: {})", node.location.line, node.location.column)));
+        },
+    };
+    Ok(output)
+}
+
+/// Compiles a whole file, keeping only the value of the last top-level node on the stack
+pub fn compile_ast(ast: &[ASTNode]) -> Result<Vec<Instruction>, VmError> {
+    let nodes: Vec<&ASTNode> = ast.iter().collect();
+    compile_sequence(&nodes)
+}
+
+#[derive(Debug)]
+struct CallFrame {
+    locals: HashMap<String, Value>,
+    return_address: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct VM {
+    pub stack: Vec<Value>,
+    pub vars: HashMap<String, Value>,
+    frames: Vec<CallFrame>,
+}
+
+impl VM {
+    pub fn new() -> Self {
+        VM::default()
+    }
+
+    fn pop(&mut self) -> Result<Value, VmError> {
+        self.stack.pop().ok_or(VmError::new(String::from("Tried to pop from an empty stack")))
+    }
+
+    // Locals from the current function call are checked first, then the top-level variables.
+    fn load(&self, name: &str) -> Result<Value, VmError> {
+        self.frames.last()
+            .and_then(|frame| frame.locals.get(name))
+            .or_else(|| self.vars.get(name))
+            .cloned()
+            .ok_or(VmError::new(format!("Undefined variable '{}'", name)))
+    }
+
+    fn store(&mut self, name: &str, value: Value) {
+        match self.frames.last_mut() {
+            Some(frame) => frame.locals.insert(name.to_string(), value),
+            None => self.vars.insert(name.to_string(), value),
+        };
+    }
+
+    fn arithmetic(&mut self, instruction: &Instruction) -> Result<Value, VmError> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        let value = match (instruction, left, right) {
+            (Instruction::Div, Value::Int(_), Value::Int(0)) => return Err(VmError::new(String::from("Division by zero"))),
+            (Instruction::Add, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_add(b)),
+            (Instruction::Sub, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_sub(b)),
+            (Instruction::Mul, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_mul(b)),
+            (Instruction::Div, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_div(b)),
+            (Instruction::Add, Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+            (Instruction::Sub, Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+            (Instruction::Mul, Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+            (Instruction::Div, Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+            (Instruction::Add, Value::Str(a), Value::Str(b)) => Value::Str(a + &b),
+            (instruction, left, right) => return Err(VmError::new(format!("Cannot apply {:?} to {} and {}", instruction, left, right))),
+        };
+        Ok(value)
+    }
+
+    pub fn run(&mut self, instructions: &[Instruction]) -> Result<Value, VmError> {
+        let mut pc = 0;
+        while let Some(instruction) = instructions.get(pc) {
+            pc += 1;
+            match instruction {
+                Instruction::Push(value) => self.stack.push(value.clone()),
+                Instruction::Pop => {
+                    self.pop()?;
+                },
+                Instruction::Add | Instruction::Sub | Instruction::Mul | Instruction::Div => {
+                    let value = self.arithmetic(instruction)?;
+                    self.stack.push(value);
+                },
+                Instruction::Load(name) => {
+                    let value = self.load(name)?;
+                    self.stack.push(value);
+                },
+                Instruction::Store(name) => {
+                    let value = self.stack.last().cloned().ok_or(VmError::new(String::from("Tried to store from an empty stack")))?;
+                    self.store(name, value);
+                },
+                Instruction::Call(argument_count) => {
+                    let argument = match argument_count {
+                        0 => None,
+                        1 => Some(self.pop()?),
+                        count => return Err(VmError::new(format!("Functions take at most one argument, but {} were given", count))),
+                    };
+                    match self.pop()? {
+                        Value::Fun(parameter, address, captured) => {
+                            let mut locals = captured;
+                            if let Some(argument) = argument {
+                                if !parameter.is_empty() {
+                                    locals.insert(parameter, argument);
+                                }
+                            }
+                            self.frames.push(CallFrame { locals, return_address: pc });
+                            pc = address;
+                        },
+                        other => return Err(VmError::new(format!("Cannot call {} as a function", other))),
+                    }
+                },
+                Instruction::Return => {
+                    let frame = self.frames.pop().ok_or(VmError::new(String::from("Tried to return outside of a function")))?;
+                    pc = frame.return_address;
+                },
+                Instruction::Jump(offset) => pc = skip(pc, *offset)?,
+                Instruction::JumpIfFalse(offset) => {
+                    match self.pop()? {
+                        Value::Bool(false) => pc = skip(pc, *offset)?,
+                        Value::Bool(true) => {},
+                        other => return Err(VmError::new(format!("Expected a boolean condition, but found {}", other))),
+                    }
+                },
+                Instruction::MakeFunc(parameter, body_length) => {
+                    let captured = self.frames.last().map(|frame| frame.locals.clone()).unwrap_or_default();
+                    self.stack.push(Value::Fun(parameter.clone(), pc, captured));
+                    pc = skip(pc, *body_length)?;
+                },
+            }
+        }
+        Ok(self.stack.last().cloned().unwrap_or(Value::Unit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str) -> Value {
+        let ParseFileResult { nodes, errors } = parse_file(text.to_string(), None);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let instructions = compile_ast(&nodes).unwrap();
+        VM::new().run(&instructions).unwrap()
+    }
+
+    #[test]
+    fn adds_two_integers() {
+        assert_eq!(run("(+ 1 2)"), Value::Int(3));
+    }
+
+    #[test]
+    fn scope_keeps_the_last_value() {
+        assert_eq!(run("{ let x = 1 let y = (+ x 2) (* y 3) }"), Value::Int(9));
+    }
+
+    #[test]
+    fn if_else_takes_one_branch() {
+        assert_eq!(run("if true then 1 else 2"), Value::Int(1));
+        assert_eq!(run("if false then 1 else 2"), Value::Int(2));
+        assert_eq!(run("(+ (if false then 1 else { let x = 3 x }) 10)"), Value::Int(13));
+    }
+    #[test]
+    fn huge_offsets_are_errors() {
+        for instruction in [Instruction::Jump(usize::MAX), Instruction::MakeFunc(String::from("x"), usize::MAX)] {
+            assert!(VM::new().run(&[Instruction::Push(Value::Unit), instruction]).is_err());
+        }
+        let instructions = [Instruction::Push(Value::Bool(false)), Instruction::JumpIfFalse(usize::MAX)];
+        assert!(VM::new().run(&instructions).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fs, thread, time::{Duration, SystemTime, UNIX_EPOCH}};
+use crate::AST::*;
+use crate::visitor::{accept_all, NodeCounter};
+
+// How often the file's modified time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Editors often save a file in several writes, so a change has to sit still this long before the file is re-parsed.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Formats the current time of day as HH:MM:SS (UTC).
+fn timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0);
+    format!("{:02}:{:02}:{:02}", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60)
+}
+
+fn parse_and_print(path: &str) {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            // Parse errors are already written to stderr by parse_ast_text.
+            let nodes = parse_ast_text(contents);
+            let mut counter = NodeCounter::default();
+            accept_all(&nodes, &mut counter);
+            println!("[{}] Parsed {} ({} nodes)", timestamp(), path, counter.count);
+            for node in &nodes {
+                println!("{}", node.display_with_location());
+            }
+        },
+        Err(err) => eprintln!("[{}] {}: {}", timestamp(), path, err),
+    }
+}
+
+// Decides when a changed file should be re-parsed, given the modified times seen on each poll.
+struct Debouncer {
+    last_seen: Option<SystemTime>,
+    // The modified time of a change that is still waiting out the debounce, along with when it was first noticed.
+    pending: Option<(Option<SystemTime>, SystemTime)>,
+}
+
+impl Debouncer {
+    fn new(last_seen: Option<SystemTime>) -> Debouncer {
+        Debouncer { last_seen, pending: None }
+    }
+
+    // Returns true when the file has changed and then sat still for the whole debounce.
+    fn poll(&mut self, current: Option<SystemTime>, now: SystemTime) -> bool {
+        match self.pending {
+            Some((pending_time, noticed_at)) if pending_time == current && now.duration_since(noticed_at).unwrap_or(DEBOUNCE) >= DEBOUNCE => {
+                self.pending = None;
+                self.last_seen = current;
+                true
+            },
+            // Still waiting out the debounce.
+            Some((pending_time, _)) if pending_time == current => false,
+            // Another write happened during the debounce, so the wait starts over.
+            Some(_) => {
+                self.pending = Some((current, now));
+                false
+            },
+            None if current != self.last_seen => {
+                self.pending = Some((current, now));
+                false
+            },
+            None => false,
+        }
+    }
+}
+
+/// Parses the file, then keeps re-parsing it whenever it changes. This never returns.
+pub fn watch_file(path: &str) {
+    parse_and_print(path);
+    let mut debouncer = Debouncer::new(modified_time(path));
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        if debouncer.poll(modified_time(path), SystemTime::now()) {
+            parse_and_print(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn unchanged_file_is_not_reparsed() {
+        let mut debouncer = Debouncer::new(Some(at(0)));
+        assert!(!debouncer.poll(Some(at(0)), at(1000)));
+        assert!(!debouncer.poll(Some(at(0)), at(5000)));
+    }
+
+    #[test]
+    fn change_is_reparsed_once_it_sits_still() {
+        let mut debouncer = Debouncer::new(Some(at(0)));
+        assert!(!debouncer.poll(Some(at(1000)), at(1000)));
+        assert!(!debouncer.poll(Some(at(1000)), at(1100)));
+        assert!(debouncer.poll(Some(at(1000)), at(1200)));
+        assert!(!debouncer.poll(Some(at(1000)), at(1500)));
+    }
+
+    #[test]
+    fn another_write_restarts_the_wait() {
+        let mut debouncer = Debouncer::new(Some(at(0)));
+        assert!(!debouncer.poll(Some(at(1000)), at(1000)));
+        assert!(!debouncer.poll(Some(at(1150)), at(1150)));
+        assert!(!debouncer.poll(Some(at(1150)), at(1250)));
+        assert!(debouncer.poll(Some(at(1150)), at(1350)));
+    }
+
+    #[test]
+    fn deleted_file_counts_as_a_change() {
+        let mut debouncer = Debouncer::new(Some(at(0)));
+        assert!(!debouncer.poll(None, at(1000)));
+        assert!(debouncer.poll(None, at(1200)));
+    }
+}